@@ -1,18 +1,69 @@
 
 use std::fmt::{Display, Formatter};
 
+use serde::Serialize;
 use warp::{Rejection, Reply};
 use warp::body::BodyDeserializeError;
 use warp::cors::CorsForbidden;
-use warp::http::StatusCode;
-use warp::reject::Reject;
+use warp::http::{header, StatusCode};
+use warp::reject::{
+    InvalidHeader, InvalidQuery, LengthRequired, MethodNotAllowed, MissingHeader, PayloadTooLarge, Reject,
+    UnsupportedMediaType,
+};
+use warp::reply::Response;
+
+// 校验失败的一个字段，field 是 JSON 中的字段名，例如 title、tags[2]
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub code: &'static str,
+    pub message: String,
+}
 
 #[derive(Debug)]
 pub enum Error {
     ParseError(std::num::ParseIntError),
-    MissingParameters,
-    InvalidRange, // 可以添加一个错误类型表示 start >= end
+    MissingSearchQuery,
+    InvalidRange, // start > end
+    InvalidSort(String),
+    InvalidTagMatch(String),
+    InvalidCursor,
+    InvalidFormat(String),
+    InvalidLimit(usize),
+    UnknownParameter(String),
+    ConflictingParameters(String, String),
+    IdMismatch(String, String),
     QuestionNotFound,
+    QuestionAlreadyExists(String),
+    PreconditionFailed,
+    Conflict(String),
+    Unauthorized,
+    Forbidden(String),
+    PayloadTooLarge(u64),
+    UnsupportedMediaType(String),
+    InvalidBody(String),
+    UnsupportedPatchFormat(String),
+    InvalidPatch(String),
+    PatchConflict(String),
+    InvalidQuestion(String),
+    Validation(Vec<FieldError>),
+    AnswerNotFound,
+    AnswerAlreadyExists(String),
+    RevisionNotFound(u64),
+    EmptyTag,
+    TagNotFound(String),
+    TagAliasNotFound(String),
+    InvalidTagAlias(String),
+    UnknownQuestion(String),
+    InternalError(String),
+    DatabaseQueryError(sqlx::Error),
+    JournalError(std::io::Error),
+    SeedReadError(std::io::Error),
+    SeedParseError {
+        line: usize,
+        column: usize,
+        message: String,
+    },
 }
 
 impl Display for Error {
@@ -21,42 +72,294 @@ impl Display for Error {
             Error::ParseError(ref err) => {
                 write!(f, "Cannot parse parameter: {}", err)
             },
-            Error::MissingParameters => write!(f, "Missing 'start' or 'end' parameter"), // 消息更清晰
-            Error::InvalidRange => write!(f, "'start' must be less than 'end'"),
+            Error::MissingSearchQuery => write!(f, "Missing search query 'q'"),
+            Error::InvalidRange => write!(f, "'start' must not be greater than 'end'"),
+            Error::InvalidCursor => write!(f, "'cursor' is malformed or does not match the sort order"),
+            Error::InvalidFormat(ref format) => {
+                write!(f, "Unknown format '{}', expected 'envelope' or 'array'", format)
+            },
+            Error::InvalidLimit(max) => write!(f, "Page size must be between 1 and {}", max),
+            Error::UnknownParameter(ref key) => write!(f, "Unknown query parameter '{}'", key),
+            Error::ConflictingParameters(ref a, ref b) => {
+                write!(f, "'{}' cannot be combined with '{}'", a, b)
+            },
+            Error::IdMismatch(ref path, ref body) => {
+                write!(f, "id '{}' in the body does not match id '{}' in the path", body, path)
+            },
+            Error::InvalidSort(ref sort) => write!(f, "Cannot sort by '{}', expected 'created' or 'id'", sort),
+            Error::InvalidTagMatch(ref mode) => {
+                write!(f, "Unknown tag_match '{}', expected 'any' or 'all'", mode)
+            },
             Error::QuestionNotFound => write!(f, "question not found"),
+            Error::QuestionAlreadyExists(ref id) => write!(f, "question '{}' already exists", id),
+            Error::UnsupportedPatchFormat(ref content_type) => write!(
+                f,
+                "Unsupported patch format '{}', expected application/merge-patch+json or application/json-patch+json",
+                content_type
+            ),
+            Error::InvalidPatch(ref message) => write!(f, "Cannot parse patch: {}", message),
+            Error::PatchConflict(ref message) => write!(f, "Cannot apply patch: {}", message),
+            Error::InvalidQuestion(ref message) => write!(f, "Invalid question: {}", message),
+            Error::Validation(ref errors) => {
+                let fields: Vec<String> = errors.iter().map(|e| format!("{} {}", e.field, e.message)).collect();
+                write!(f, "Invalid input: {}", fields.join("; "))
+            },
+            Error::PreconditionFailed => write!(f, "question has been modified, If-Match does not match its ETag"),
+            Error::Conflict(ref message) => write!(f, "{}", message),
+            Error::Unauthorized => write!(f, "Authentication is required"),
+            Error::Forbidden(ref action) => write!(f, "Not allowed to {}", action),
+            Error::PayloadTooLarge(limit) => write!(f, "Request body must not be larger than {} bytes", limit),
+            Error::UnsupportedMediaType(ref content_type) => {
+                write!(f, "Unsupported Content-Type '{}'", content_type)
+            },
+            Error::InvalidBody(ref message) => write!(f, "Cannot parse request body: {}", message),
+            Error::AnswerNotFound => write!(f, "answer not found"),
+            Error::AnswerAlreadyExists(ref id) => write!(f, "answer '{}' already exists", id),
+            Error::RevisionNotFound(number) => write!(f, "revision {} not found", number),
+            Error::EmptyTag => write!(f, "Tag must not be empty"),
+            Error::TagNotFound(ref tag) => write!(f, "tag '{}' is not used by any question", tag),
+            Error::TagAliasNotFound(ref alias) => write!(f, "tag alias '{}' not found", alias),
+            Error::InvalidTagAlias(ref alias) => write!(f, "'{}' cannot be an alias of itself", alias),
+            Error::UnknownQuestion(ref id) => write!(f, "question '{}' does not exist", id),
+            Error::InternalError(ref message) => write!(f, "Internal error: {}", message),
+            Error::DatabaseQueryError(ref err) => write!(f, "Query could not be executed: {}", err),
+            Error::JournalError(ref err) => write!(f, "Cannot write journal: {}", err),
+            Error::SeedReadError(ref err) => write!(f, "Cannot read seed file: {}", err),
+            Error::SeedParseError { line, column, ref message } => {
+                write!(f, "Cannot parse seed file at line {}, column {}: {}", line, column, message)
+            },
         }
     }
 }
 impl Reject for Error {}
 
-pub async fn return_error(r: Rejection) -> Result<impl Reply, Rejection> {
+impl Error {
+    // 稳定的错误码，客户端应该根据它而不是提示文字判断错误类型
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::ParseError(_) => "invalid_parameter",
+            Error::MissingSearchQuery => "missing_search_query",
+            Error::InvalidRange => "invalid_range",
+            Error::InvalidSort(_) => "invalid_sort",
+            Error::InvalidTagMatch(_) => "invalid_tag_match",
+            Error::InvalidCursor => "invalid_cursor",
+            Error::InvalidFormat(_) => "invalid_format",
+            Error::InvalidLimit(_) => "invalid_limit",
+            Error::UnknownParameter(_) => "unknown_parameter",
+            Error::ConflictingParameters(_, _) => "conflicting_parameters",
+            Error::IdMismatch(_, _) => "id_mismatch",
+            Error::QuestionNotFound => "question_not_found",
+            Error::QuestionAlreadyExists(_) => "question_already_exists",
+            Error::PreconditionFailed => "precondition_failed",
+            Error::Conflict(_) => "conflict",
+            Error::Unauthorized => "unauthorized",
+            Error::Forbidden(_) => "forbidden",
+            Error::PayloadTooLarge(_) => "payload_too_large",
+            Error::UnsupportedMediaType(_) => "unsupported_media_type",
+            Error::InvalidBody(_) => "invalid_body",
+            Error::UnsupportedPatchFormat(_) => "unsupported_patch_format",
+            Error::InvalidPatch(_) => "invalid_patch",
+            Error::PatchConflict(_) => "patch_conflict",
+            Error::InvalidQuestion(_) => "invalid_question",
+            Error::Validation(_) => "validation_failed",
+            Error::AnswerNotFound => "answer_not_found",
+            Error::AnswerAlreadyExists(_) => "answer_already_exists",
+            Error::RevisionNotFound(_) => "revision_not_found",
+            Error::EmptyTag => "empty_tag",
+            Error::TagNotFound(_) => "tag_not_found",
+            Error::TagAliasNotFound(_) => "tag_alias_not_found",
+            Error::InvalidTagAlias(_) => "invalid_tag_alias",
+            Error::UnknownQuestion(_) => "unknown_question",
+            Error::InternalError(_) => "internal_error",
+            Error::DatabaseQueryError(_) | Error::JournalError(_) => "storage_error",
+            Error::SeedReadError(_) | Error::SeedParseError { .. } => "seed_error",
+        }
+    }
+
+    // 错误类型的简短说明，同一种错误总是相同，具体原因放在 detail 中
+    pub fn title(&self) -> &'static str {
+        match self {
+            Error::ParseError(_) => "Invalid parameter",
+            Error::MissingSearchQuery => "Missing search query",
+            Error::InvalidRange => "Invalid range",
+            Error::InvalidSort(_) => "Invalid sort order",
+            Error::InvalidTagMatch(_) => "Invalid tag match mode",
+            Error::InvalidCursor => "Invalid cursor",
+            Error::InvalidFormat(_) => "Invalid response format",
+            Error::InvalidLimit(_) => "Invalid page size",
+            Error::UnknownParameter(_) => "Unknown query parameter",
+            Error::ConflictingParameters(_, _) => "Conflicting query parameters",
+            Error::IdMismatch(_, _) => "Id mismatch",
+            Error::QuestionNotFound => "Question not found",
+            Error::QuestionAlreadyExists(_) => "Question already exists",
+            Error::PreconditionFailed => "Precondition failed",
+            Error::Conflict(_) => "Conflict",
+            Error::Unauthorized => "Unauthorized",
+            Error::Forbidden(_) => "Forbidden",
+            Error::PayloadTooLarge(_) => "Payload too large",
+            Error::UnsupportedMediaType(_) => "Unsupported media type",
+            Error::InvalidBody(_) => "Invalid request body",
+            Error::UnsupportedPatchFormat(_) => "Unsupported patch format",
+            Error::InvalidPatch(_) => "Invalid patch",
+            Error::PatchConflict(_) => "Patch cannot be applied",
+            Error::InvalidQuestion(_) => "Invalid question",
+            Error::Validation(_) => "Validation failed",
+            Error::AnswerNotFound => "Answer not found",
+            Error::AnswerAlreadyExists(_) => "Answer already exists",
+            Error::RevisionNotFound(_) => "Revision not found",
+            Error::EmptyTag => "Empty tag",
+            Error::TagNotFound(_) => "Tag not found",
+            Error::TagAliasNotFound(_) => "Tag alias not found",
+            Error::InvalidTagAlias(_) => "Invalid tag alias",
+            Error::UnknownQuestion(_) => "Unknown question",
+            Error::InternalError(_) => "Internal server error",
+            Error::DatabaseQueryError(_) | Error::JournalError(_) => "Storage error",
+            Error::SeedReadError(_) | Error::SeedParseError { .. } => "Invalid seed file",
+        }
+    }
+}
+
+// 错误对应的 HTTP 状态码，由每种错误自己声明
+pub trait HttpStatus {
+    fn status(&self) -> StatusCode;
+
+    // 5xx 错误的细节只写日志，不返回给客户端
+    fn is_internal(&self) -> bool {
+        self.status().is_server_error()
+    }
+}
+
+impl HttpStatus for Error {
+    fn status(&self) -> StatusCode {
+        match self {
+            Error::ParseError(_) => StatusCode::BAD_REQUEST,
+            Error::MissingSearchQuery => StatusCode::BAD_REQUEST,
+            Error::InvalidRange => StatusCode::BAD_REQUEST,
+            Error::InvalidSort(_) => StatusCode::BAD_REQUEST,
+            Error::InvalidTagMatch(_) => StatusCode::BAD_REQUEST,
+            Error::InvalidCursor => StatusCode::BAD_REQUEST,
+            Error::InvalidFormat(_) => StatusCode::BAD_REQUEST,
+            Error::InvalidLimit(_) => StatusCode::BAD_REQUEST,
+            Error::UnknownParameter(_) => StatusCode::BAD_REQUEST,
+            Error::ConflictingParameters(_, _) => StatusCode::BAD_REQUEST,
+            Error::IdMismatch(_, _) => StatusCode::BAD_REQUEST,
+            Error::QuestionNotFound => StatusCode::NOT_FOUND,
+            Error::QuestionAlreadyExists(_) => StatusCode::CONFLICT,
+            // 客户端持有的版本已经过期，需要重新获取后再修改
+            Error::PreconditionFailed => StatusCode::PRECONDITION_FAILED,
+            Error::Conflict(_) => StatusCode::CONFLICT,
+            Error::Unauthorized => StatusCode::UNAUTHORIZED,
+            Error::Forbidden(_) => StatusCode::FORBIDDEN,
+            Error::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Error::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Error::InvalidBody(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::UnsupportedPatchFormat(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Error::InvalidPatch(_) => StatusCode::BAD_REQUEST,
+            Error::PatchConflict(_) => StatusCode::CONFLICT,
+            // 修改后的问题不合法
+            Error::InvalidQuestion(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::AnswerNotFound => StatusCode::NOT_FOUND,
+            Error::AnswerAlreadyExists(_) => StatusCode::CONFLICT,
+            Error::RevisionNotFound(_) => StatusCode::NOT_FOUND,
+            Error::EmptyTag => StatusCode::BAD_REQUEST,
+            Error::TagNotFound(_) => StatusCode::NOT_FOUND,
+            Error::TagAliasNotFound(_) => StatusCode::NOT_FOUND,
+            Error::InvalidTagAlias(_) => StatusCode::BAD_REQUEST,
+            // 请求本身格式正确，但引用的问题不存在
+            Error::UnknownQuestion(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::DatabaseQueryError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::JournalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::SeedReadError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::SeedParseError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+// RFC 7807 problem details，所有错误响应都使用这个格式
+#[derive(Debug, Serialize)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub title: &'static str,
+    pub status: u16,
+    pub detail: String,
+    // 出错的请求路径
+    pub instance: String,
+    pub code: &'static str,
+    pub request_id: String,
+    // 校验失败时每个字段的错误
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+// 把 Rejection 转换成 application/problem+json 响应
+pub fn problem_response(r: &Rejection, request_id: &str, instance: &str) -> Response {
+    let (status, code, title, detail) = describe(r);
+    let problem = Problem {
+        kind: format!("/problems/{}", code),
+        title,
+        status: status.as_u16(),
+        detail,
+        instance: instance.to_string(),
+        code,
+        request_id: request_id.to_string(),
+        errors: match r.find::<Error>() {
+            Some(Error::Validation(errors)) => errors.clone(),
+            _ => Vec::new(),
+        },
+    };
+    let reply = warp::reply::with_status(warp::reply::json(&problem), status);
+    let mut response = warp::reply::with_header(reply, header::CONTENT_TYPE, "application/problem+json").into_response();
+    // 401 需要告诉客户端使用哪种认证方式
+    if let Some(Error::Unauthorized) = r.find::<Error>() {
+        response
+            .headers_mut()
+            .insert(header::WWW_AUTHENTICATE, header::HeaderValue::from_static("Bearer"));
+    }
+    response
+}
+
+fn describe(r: &Rejection) -> (StatusCode, &'static str, &'static str, String) {
     if let Some(error) = r.find::<Error>() {
-        // 对客户端参数错误使用 BAD_REQUEST (400)
-        Ok(warp::reply::with_status(
-            error.to_string(),
-            StatusCode::BAD_REQUEST // <--- 修改点
-        ))
-    } else if let Some(cors_error) = r.find::<CorsForbidden>() {
-        Ok(warp::reply::with_status(
-            cors_error.to_string(),
-            StatusCode::FORBIDDEN,
-        ))
-    } else if let Some (error) = r.find::<BodyDeserializeError>(){
-        Ok(warp::reply::with_status(
-            error.to_string(),
-            StatusCode::UNPROCESSABLE_ENTITY,
-        ))
+        let detail = match error.is_internal() {
+            true => {
+                log::error!("Internal error: {:?}", error);
+                "Internal Server Error".to_string()
+            }
+            false => error.to_string(),
+        };
+        (error.status(), error.code(), error.title(), detail)
+    } else if let Some(error) = r.find::<CorsForbidden>() {
+        (StatusCode::FORBIDDEN, "cors_forbidden", "CORS request forbidden", error.to_string())
+    } else if let Some(error) = r.find::<BodyDeserializeError>() {
+        (StatusCode::UNPROCESSABLE_ENTITY, "invalid_body", "Invalid request body", error.to_string())
+    } else if let Some(error) = r.find::<UnsupportedMediaType>() {
+        (StatusCode::UNSUPPORTED_MEDIA_TYPE, "unsupported_media_type", "Unsupported media type", error.to_string())
+    } else if let Some(error) = r.find::<InvalidQuery>() {
+        (StatusCode::BAD_REQUEST, "invalid_query", "Invalid query string", error.to_string())
+    } else if let Some(error) = r.find::<InvalidHeader>() {
+        (StatusCode::BAD_REQUEST, "invalid_header", "Invalid request header", error.to_string())
+    } else if let Some(error) = r.find::<MissingHeader>() {
+        (StatusCode::BAD_REQUEST, "missing_header", "Missing request header", error.to_string())
+    } else if let Some(error) = r.find::<LengthRequired>() {
+        (StatusCode::LENGTH_REQUIRED, "length_required", "Length required", error.to_string())
+    } else if let Some(error) = r.find::<PayloadTooLarge>() {
+        (StatusCode::PAYLOAD_TOO_LARGE, "payload_too_large", "Payload too large", error.to_string())
     } else if r.is_not_found() { // 使用 is_not_found() 更明确
-        Ok(warp::reply::with_status(
-            "Route not found".to_string(),
-            StatusCode::NOT_FOUND,
-        ))
+        (StatusCode::NOT_FOUND, "route_not_found", "Route not found", "Route not found".to_string())
+    } else if let Some(error) = r.find::<MethodNotAllowed>() {
+        // 路径存在但方法不对，路由都在匹配完路径之后才检查请求方法
+        (StatusCode::METHOD_NOT_ALLOWED, "method_not_allowed", "Method not allowed", error.to_string())
     } else {
         // 处理其他未预期的 rejection
-        eprintln!("Unhandled rejection: {:?}", r); // 最好记录下未处理的错误
-        Ok(warp::reply::with_status(
-            "Internal Server Error".to_string(),
+        log::error!("Unhandled rejection: {:?}", r);
+        (
             StatusCode::INTERNAL_SERVER_ERROR,
-        ))
+            "internal_error",
+            "Internal server error",
+            "Internal Server Error".to_string(),
+        )
     }
 }
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS questions (
+    id TEXT PRIMARY KEY NOT NULL,
+    title TEXT NOT NULL,
+    content TEXT NOT NULL,
+    -- tags 以 JSON 数组保存，NULL 表示没有标签
+    tags TEXT
+);
+
+CREATE TABLE IF NOT EXISTS answers (
+    id TEXT PRIMARY KEY NOT NULL,
+    content TEXT NOT NULL,
+    question_id TEXT NOT NULL
+);
@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS answers_question_id ON answers (question_id);
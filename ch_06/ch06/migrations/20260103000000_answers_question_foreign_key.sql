@@ -0,0 +1,16 @@
+-- 给 answers.question_id 加上外键，SQLite 不支持 ALTER TABLE ADD CONSTRAINT，只能重建表
+-- 已经指向不存在问题的回答不会被迁移
+CREATE TABLE answers_new (
+    id TEXT PRIMARY KEY NOT NULL,
+    content TEXT NOT NULL,
+    question_id TEXT NOT NULL REFERENCES questions (id) ON DELETE CASCADE
+);
+
+INSERT INTO answers_new (id, content, question_id)
+    SELECT id, content, question_id FROM answers
+    WHERE question_id IN (SELECT id FROM questions);
+
+DROP TABLE answers;
+ALTER TABLE answers_new RENAME TO answers;
+
+CREATE INDEX answers_question_id ON answers (question_id);
@@ -0,0 +1,6 @@
+-- 按创建顺序分页需要一个稳定的序号，rowid 在 VACUUM 之后可能会变化
+ALTER TABLE questions ADD COLUMN created_seq INTEGER NOT NULL DEFAULT 0;
+
+UPDATE questions SET created_seq = rowid;
+
+CREATE INDEX questions_created_seq ON questions (created_seq);
@@ -0,0 +1,12 @@
+-- 标签 → 问题的索引表，按标签过滤时不需要解析每一行的 tags JSON
+CREATE TABLE question_tags (
+    question_id TEXT NOT NULL REFERENCES questions (id) ON DELETE CASCADE,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (question_id, tag)
+);
+
+CREATE INDEX question_tags_tag ON question_tags (tag, question_id);
+
+INSERT OR IGNORE INTO question_tags (question_id, tag)
+    SELECT questions.id, json_each.value FROM questions, json_each(questions.tags)
+    WHERE questions.tags IS NOT NULL AND json_valid(questions.tags);
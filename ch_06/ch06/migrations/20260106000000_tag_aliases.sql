@@ -0,0 +1,19 @@
+-- 标签别名，写入问题时 alias 会被替换成 tag
+CREATE TABLE tag_aliases (
+    alias TEXT PRIMARY KEY NOT NULL,
+    tag TEXT NOT NULL
+);
+
+-- 已有的标签统一去掉首尾空白并转成小写，然后重建 question_tags
+UPDATE questions SET tags = (
+    SELECT json_group_array(DISTINCT lower(trim(json_each.value)))
+    FROM json_each(questions.tags)
+    WHERE trim(json_each.value) <> ''
+)
+WHERE tags IS NOT NULL AND json_valid(tags);
+
+DELETE FROM question_tags;
+
+INSERT OR IGNORE INTO question_tags (question_id, tag)
+    SELECT questions.id, json_each.value FROM questions, json_each(questions.tags)
+    WHERE questions.tags IS NOT NULL AND json_valid(questions.tags);
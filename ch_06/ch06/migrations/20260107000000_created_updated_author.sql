@@ -0,0 +1,17 @@
+-- 创建时间、修改时间（RFC 3339）和作者，由服务端维护
+-- 已有的数据没有这些信息，创建时间和修改时间取迁移时的时间，作者为 anonymous
+ALTER TABLE questions ADD COLUMN created_at TEXT NOT NULL DEFAULT '';
+ALTER TABLE questions ADD COLUMN updated_at TEXT NOT NULL DEFAULT '';
+ALTER TABLE questions ADD COLUMN author TEXT NOT NULL DEFAULT 'anonymous';
+
+UPDATE questions SET
+    created_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'),
+    updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now');
+
+ALTER TABLE answers ADD COLUMN created_at TEXT NOT NULL DEFAULT '';
+ALTER TABLE answers ADD COLUMN updated_at TEXT NOT NULL DEFAULT '';
+ALTER TABLE answers ADD COLUMN author TEXT NOT NULL DEFAULT 'anonymous';
+
+UPDATE answers SET
+    created_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'),
+    updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now');
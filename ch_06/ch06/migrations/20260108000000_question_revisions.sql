@@ -0,0 +1,16 @@
+-- 问题的历史版本，每个版本保存修改后的完整内容
+CREATE TABLE question_revisions (
+    question_id TEXT NOT NULL REFERENCES questions (id) ON DELETE CASCADE,
+    number INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    content TEXT NOT NULL,
+    tags TEXT,
+    editor TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    summary TEXT,
+    PRIMARY KEY (question_id, number)
+);
+
+-- 已有的问题以当前内容作为第 1 个版本
+INSERT INTO question_revisions (question_id, number, title, content, tags, editor, created_at)
+    SELECT id, 1, title, content, tags, author, updated_at FROM questions;
@@ -0,0 +1,8 @@
+-- 删除的问题先放进回收站，可以恢复，超过保留时间后才真正删除
+ALTER TABLE questions ADD COLUMN deleted_at TEXT;
+ALTER TABLE questions ADD COLUMN deleted_by TEXT;
+
+CREATE INDEX questions_deleted_at ON questions (deleted_at);
+
+-- 除了回收站相关的操作，所有查询都只读取没有被删除的问题
+CREATE VIEW live_questions AS SELECT * FROM questions WHERE deleted_at IS NULL;
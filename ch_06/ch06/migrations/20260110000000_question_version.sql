@@ -0,0 +1,6 @@
+-- 每次修改问题时加 1，用于 ETag 和 If-Match
+ALTER TABLE questions ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+
+-- 重新创建视图，确保包含新加的列
+DROP VIEW live_questions;
+CREATE VIEW live_questions AS SELECT * FROM questions WHERE deleted_at IS NULL;
@@ -0,0 +1,110 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::{Parser, ValueEnum};
+
+use crate::types::pagination::{PageLimits, DEFAULT_LIMIT, MAX_LIMIT};
+use crate::types::validation::{
+    ValidationLimits, MAX_ANSWER_LENGTH, MAX_CONTENT_LENGTH, MAX_TAGS, MAX_TAG_LENGTH, MAX_TITLE_LENGTH,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Backend {
+    /// 进程内的 HashMap，重启后数据丢失
+    Memory,
+    /// 嵌入式 SQLite 数据库文件
+    Sqlite,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum IdStrategy {
+    /// 随机的 UUID v4
+    Uuid,
+    /// 按生成时间排序的 ULID
+    Ulid,
+}
+
+/// 服务启动参数，命令行和环境变量都可以设置
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Config {
+    /// 存储后端
+    #[arg(long, env = "STORE_BACKEND", value_enum, default_value_t = Backend::Memory)]
+    pub backend: Backend,
+    /// SQLite 数据库地址，仅在 --backend sqlite 时使用
+    #[arg(long, env = "DATABASE_URL", default_value = "sqlite://questions.db")]
+    pub database_url: String,
+    /// 启动时导入的问题数据文件（以 id 为键的对象或 Question 数组），不指定则从空存储启动
+    #[arg(long, env = "SEED_FILE")]
+    pub seed: Option<PathBuf>,
+    /// 内存存储的数据目录，设置后每次修改都会写入日志并定期生成快照，重启时自动恢复
+    #[arg(long, env = "DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
+    /// 生成快照并清空日志的间隔（秒）
+    #[arg(long, env = "SNAPSHOT_INTERVAL", default_value_t = 60)]
+    pub snapshot_interval: u64,
+    /// 新建问题和回答时使用的 id 生成方式
+    #[arg(long, env = "ID_STRATEGY", value_enum, default_value_t = IdStrategy::Uuid)]
+    pub id_strategy: IdStrategy,
+    /// 允许 POST /questions 指定 id，id 已存在时返回 409
+    #[arg(long, env = "ALLOW_CLIENT_IDS")]
+    pub allow_client_ids: bool,
+    /// 列表接口没有指定 limit 时每页的数量
+    #[arg(long, env = "DEFAULT_PAGE_SIZE", default_value_t = DEFAULT_LIMIT)]
+    pub default_page_size: usize,
+    /// 列表接口每页数量的上限
+    #[arg(long, env = "MAX_PAGE_SIZE", default_value_t = MAX_LIMIT)]
+    pub max_page_size: usize,
+    /// 删除的问题在回收站中保留的时间，超过后彻底删除，例如 30d、12h
+    #[arg(long, env = "TRASH_RETENTION", value_parser = humantime::parse_duration, default_value = "30d")]
+    pub trash_retention: Duration,
+    /// 请求体的最大字节数
+    #[arg(long, env = "MAX_BODY_SIZE", default_value_t = 64 * 1024)]
+    pub max_body_size: u64,
+    /// 问题标题的最大字符数
+    #[arg(long, env = "MAX_TITLE_LENGTH", default_value_t = MAX_TITLE_LENGTH)]
+    pub max_title_length: usize,
+    /// 问题内容的最大字符数
+    #[arg(long, env = "MAX_CONTENT_LENGTH", default_value_t = MAX_CONTENT_LENGTH)]
+    pub max_content_length: usize,
+    /// 回答内容的最大字符数
+    #[arg(long, env = "MAX_ANSWER_LENGTH", default_value_t = MAX_ANSWER_LENGTH)]
+    pub max_answer_length: usize,
+    /// 每个问题最多的标签数
+    #[arg(long, env = "MAX_TAGS", default_value_t = MAX_TAGS)]
+    pub max_tags: usize,
+    /// 每个标签的最大字符数
+    #[arg(long, env = "MAX_TAG_LENGTH", default_value_t = MAX_TAG_LENGTH)]
+    pub max_tag_length: usize,
+    /// 管理接口（回收站列表、恢复问题、标签别名、改名和合并）的令牌，请求需带上 Authorization: Bearer <令牌>；不设置时管理接口一律返回 403
+    #[arg(long, env = "ADMIN_TOKEN")]
+    pub admin_token: Option<String>,
+}
+
+impl Config {
+    pub fn page_limits(&self) -> PageLimits {
+        PageLimits {
+            default_limit: self.default_page_size.clamp(1, self.max_page_size.max(1)),
+            max_limit: self.max_page_size.max(1),
+        }
+    }
+
+    pub fn validation_limits(&self) -> ValidationLimits {
+        ValidationLimits {
+            max_title_length: self.max_title_length,
+            max_content_length: self.max_content_length,
+            max_answer_length: self.max_answer_length,
+            max_tags: self.max_tags,
+            max_tag_length: self.max_tag_length,
+        }
+    }
+
+    // 空字符串视为没有设置
+    pub fn admin_token(&self) -> Option<String> {
+        self.admin_token.clone().filter(|token| !token.is_empty())
+    }
+
+    pub fn snapshot_interval(&self) -> Duration {
+        Duration::from_secs(self.snapshot_interval.max(1))
+    }
+}
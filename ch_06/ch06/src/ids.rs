@@ -0,0 +1,45 @@
+use std::sync::{Arc, Mutex};
+
+use crate::config::IdStrategy;
+use crate::types::question::QuestionId;
+
+// 服务端生成问题和回答的 id
+#[derive(Clone)]
+pub struct IdGenerator {
+    strategy: IdStrategy,
+    allow_client_ids: bool,
+    // 同一毫秒内生成的 ULID 依次递增，保证按生成顺序排序
+    ulid: Arc<Mutex<ulid::Generator>>,
+}
+
+impl IdGenerator {
+    pub fn new(strategy: IdStrategy, allow_client_ids: bool) -> Self {
+        IdGenerator {
+            strategy,
+            allow_client_ids,
+            ulid: Arc::new(Mutex::new(ulid::Generator::new())),
+        }
+    }
+
+    // 允许时使用客户端提供的 id（字符集和长度已在校验时检查），否则生成新的
+    pub fn question_id(&self, requested: Option<QuestionId>) -> QuestionId {
+        match requested {
+            Some(id) if self.allow_client_ids && !id.0.is_empty() => id,
+            _ => QuestionId(self.next_id()),
+        }
+    }
+
+    pub fn next_id(&self) -> String {
+        match self.strategy {
+            IdStrategy::Uuid => uuid::Uuid::new_v4().to_string(),
+            IdStrategy::Ulid => {
+                let mut generator = self.ulid.lock().unwrap();
+                // 同一毫秒内的随机部分用尽时退回到非单调的 ULID
+                generator
+                    .generate()
+                    .unwrap_or_else(|_| ulid::Ulid::new())
+                    .to_string()
+            }
+        }
+    }
+}
@@ -1,29 +1,50 @@
-use handle_errors::return_error;
+use std::sync::Arc;
+
+use clap::Parser;
 use warp::{
-    Filter,
+    Filter, Rejection,
     http::Method, // 移除未使用的 InvalidId 后，这里可能不再需要显式引入 Reject，但保留也无妨,
+    reply::Response,
 };
 
-use crate::routes::answer::add_answer;
-use crate::routes::question::{add_question, delete_question, get_questions, update_question};
-use crate::store::Store;
+use crate::routes::answer::{add_answer, delete_answer, get_answer, get_answers, new_answer_body, update_answer};
+use crate::routes::question::{
+    add_question, delete_question, get_deleted_questions, get_question, get_questions, get_revision, get_revisions,
+    patch_question, restore_question, rollback_question, update_question,
+};
+use crate::routes::{
+    admin, author, body_limit, if_match, if_none_match, json_body, validated_json, with_problem_details, REQUEST_ID_HEADER,
+};
+use crate::routes::search::search_questions;
+use crate::routes::tag::{delete_tag_alias, get_tag_aliases, get_tags, merge_tags, rename_tag, set_tag_alias};
+use crate::config::{Backend, Config};
+use crate::ids::IdGenerator;
+use crate::store::{load_seed, seed_store, spawn_purge, SharedStore, SqliteStore, Store};
 use crate::types::answer::{Answer, AnswerId};
 use crate::types::question::{Question, QuestionId};
 
+mod config;
+mod ids;
 mod routes;
+mod search;
 mod types;
 mod store;
 
 
 #[tokio::main]
-async fn main() {
-    // 准备一个示例 questions.json 文件在项目根目录
-    // 例如：
+async fn main() -> Result<(), handle_errors::Error> {
+    let config = Config::parse();
+
+    // 种子数据通过 --seed 指定，例如 cargo run -- --seed question.json
+    // 文件可以是以 id 为键的对象：
     // {
     //   "q1": { "id": "q1", "title": "First Question", "content": "Content of Q1", "tags": ["rust"] },
-    //   "q2": { "id": "q2", "title": "Second Question", "content": "Content of Q2", "tags": ["web"] },
-    //   "q3": { "id": "q3", "title": "Third Question", "content": "Content of Q3", "tags": ["warp"] }
+    //   "q2": { "id": "q2", "title": "Second Question", "content": "Content of Q2", "tags": ["web"] }
     // }
+    // 也可以是 Question 数组：
+    // [
+    //   { "id": "q3", "title": "Third Question", "content": "Content of Q3", "tags": ["warp"] }
+    // ]
     // 初始化日志记录器
     log4rs::init_file("log4rs.yaml", Default::default()).unwrap();
     log::error!("This is an error!");
@@ -39,65 +60,366 @@ async fn main() {
                  info.request_headers()
        );
     });
-    let store = Store::new();
+    let store: SharedStore = match config.backend {
+        Backend::Memory => match &config.data_dir {
+            Some(dir) => {
+                let store = Store::open(dir).await?;
+                store.spawn_compaction(config.snapshot_interval());
+                Arc::new(store)
+            }
+            None => Arc::new(Store::new()),
+        },
+        Backend::Sqlite => Arc::new(SqliteStore::new(&config.database_url).await?),
+    };
+    spawn_purge(store.clone(), config.trash_retention);
+    if let Some(path) = &config.seed {
+        let questions = load_seed(path).map_err(|e| {
+            log::error!("{}: {}", path.display(), e);
+            e
+        })?;
+        let count = seed_store(store.as_ref(), questions).await?;
+        log::info!("Seeded {} questions from {}", count, path.display());
+    }
+    let routes = routes(config, store).with(log);
+
+    warp::serve(routes)
+        .run(([127, 0, 0, 1], 3030))
+        .await;
+
+    Ok(())
+}
+
+// 所有路由。路由都先匹配路径再匹配请求方法，路径不存在时返回 404，路径存在但方法不对时返回 405
+fn routes(config: Config, store: SharedStore) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
     let store_filter = warp::any().map(move || store.clone());
 
     let id_filter = warp::any().map(|| uuid::Uuid::new_v4().to_string());
 
+    let max_body_size = config.max_body_size;
+
+    let admin = admin(config.admin_token());
+
+    let validation = config.validation_limits();
+    let validation_filter = warp::any().map(move || validation);
+
+    let limits = config.page_limits();
+    let limits_filter = warp::any().map(move || limits);
+
+    let ids = IdGenerator::new(config.id_strategy, config.allow_client_ids);
+    let ids_filter = warp::any().map(move || ids.clone());
+
     let cors = warp::cors()
         .allow_any_origin()
-        .allow_header("content-type")
-        .allow_methods(&[Method::PUT, Method::DELETE, Method::GET, Method::POST]); // GET 通常也需要允许
+        .allow_headers(["authorization", "content-type", "if-match", "if-none-match", REQUEST_ID_HEADER])
+        .expose_headers(["etag", REQUEST_ID_HEADER])
+        .allow_methods(&[Method::PUT, Method::PATCH, Method::DELETE, Method::GET, Method::POST]); // GET 通常也需要允许
 
-    let get_questions = warp::get()
-        .and(warp::path("questions"))
+    let get_questions = warp::path("questions")
         .and(warp::path::end())
-        .and(warp::query()) // 提取查询参数 HashMap<String, String>
+        .and(warp::get())
+        .and(warp::query::<Vec<(String, String)>>()) // 提取查询参数，tag 可以出现多次
+        .and(limits_filter) // 每页数量的默认值和上限
         .and(store_filter.clone()) // 注入 store
         .and(id_filter)
         .and_then(get_questions); // 调用处理函数
 
-    let add_question = warp::post()
-        .and(warp::path("questions"))
+    let get_question = warp::path("questions")
+        .and(warp::path::param::<String>())
         .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::query()) // ?include=answers 时一并返回回答
+        .and(if_none_match())
         .and(store_filter.clone())
-        .and(warp::body::json())
+        .and_then(get_question);
+
+    let add_question = warp::path("questions")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(store_filter.clone())
+        .and(ids_filter.clone())
+        .and(author())
+        .and(validated_json(max_body_size, validation))
         .and_then(add_question);
 
-    let update_question = warp::put()
-        .and(warp::path("questions"))
+    let update_question = warp::path("questions")
         .and(warp::path::param::<String>())
         .and(warp::path::end())
+        .and(warp::put())
         .and(store_filter.clone())
-        .and(warp::body::json())
+        .and(author())
+        .and(if_match())
+        .and(validated_json(max_body_size, validation))
         .and_then(update_question);
 
-    let delete_question = warp::delete()
-        .and(warp::path("questions"))
+    let patch_question = warp::path("questions")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::patch())
+        .and(store_filter.clone())
+        .and(author())
+        .and(if_match())
+        .and(validation_filter)
+        .and(warp::header::optional::<String>("content-type"))
+        .and(body_limit(max_body_size))
+        .and_then(patch_question);
+
+    let get_revisions = warp::path("questions")
+        .and(warp::path::param::<String>())
+        .and(warp::path("revisions"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(store_filter.clone())
+        .and_then(get_revisions);
+
+    let get_revision = warp::path("questions")
+        .and(warp::path::param::<String>())
+        .and(warp::path("revisions"))
+        .and(warp::path::param::<u64>())
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(store_filter.clone())
+        .and_then(get_revision);
+
+    let rollback_question = warp::path("questions")
+        .and(warp::path::param::<String>())
+        .and(warp::path("revisions"))
+        .and(warp::path::param::<u64>())
+        .and(warp::path("rollback"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(store_filter.clone())
+        .and(author())
+        .and_then(rollback_question);
+
+    let delete_question = warp::path("questions")
         .and(warp::path::param::<String>())
         .and(warp::path::end())
+        .and(warp::delete())
         .and(store_filter.clone())
+        .and(author())
+        .and(if_match())
         .and_then(delete_question);
 
-    let add_answer = warp::post()
+    let restore_question = warp::path("questions")
+        .and(warp::path::param::<String>())
+        .and(warp::path("restore"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(admin.clone())
+        .and(store_filter.clone())
+        .and_then(restore_question);
+
+    // 回收站列表，需要管理员令牌
+    let get_deleted_questions = warp::path("admin")
+        .and(warp::path("trash"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(admin.clone())
+        .and(store_filter.clone())
+        .and_then(get_deleted_questions);
+
+    let search = warp::path("search")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::query())
+        .and(limits_filter)
+        .and(store_filter.clone())
+        .and_then(search_questions);
+
+    let get_tags = warp::path("tags")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(store_filter.clone())
+        .and_then(get_tags);
+
+    let get_tag_aliases = warp::path("tags")
+        .and(warp::path("aliases"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(store_filter.clone())
+        .and_then(get_tag_aliases);
+
+    // 标签的修改会改写所有使用它的问题，需要管理员令牌
+    let set_tag_alias = warp::path("tags")
+        .and(warp::path("aliases"))
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(admin.clone())
+        .and(store_filter.clone())
+        .and(author())
+        .and(json_body(max_body_size))
+        .and_then(set_tag_alias);
+
+    let delete_tag_alias = warp::path("tags")
+        .and(warp::path("aliases"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::delete())
+        .and(admin.clone())
+        .and(store_filter.clone())
+        .and_then(delete_tag_alias);
+
+    let rename_tag = warp::path("tags")
+        .and(warp::path("rename"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(admin.clone())
+        .and(store_filter.clone())
+        .and(author())
+        .and(json_body(max_body_size))
+        .and_then(rename_tag);
+
+    let merge_tags = warp::path("tags")
+        .and(warp::path("merge"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(admin.clone())
+        .and(store_filter.clone())
+        .and(author())
+        .and(json_body(max_body_size))
+        .and_then(merge_tags);
+
+    let get_answers = warp::path("questions")
+        .and(warp::path::param::<String>())
         .and(warp::path("answers"))
         .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::query())
+        .and(limits_filter)
+        .and(store_filter.clone())
+        .and_then(get_answers);
+
+    let get_answer = warp::path("answers")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(store_filter.clone())
+        .and_then(get_answer);
+
+    let add_answer = warp::path("answers")
+        .and(warp::path::end())
+        .and(warp::post())
         .and(store_filter.clone())
-        .and(warp::body::form())
+        .and(ids_filter.clone())
+        .and(author())
+        .and(new_answer_body(max_body_size, validation))
         .and_then(add_answer);
 
-    // 注意：recover 需要放在应用 CORS *之前* 或 *之后*，取决于你想如何处理 CORS 错误
-    // 通常放在应用 CORS 之后，这样 CORS 错误（如 CorsForbidden）也能被 return_error 捕获
+    let update_answer = warp::path("answers")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(store_filter.clone())
+        .and(author())
+        .and(new_answer_body(max_body_size, validation))
+        .and_then(update_answer);
+
+    let delete_answer = warp::path("answers")
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::delete())
+        .and(store_filter.clone())
+        .and_then(delete_answer);
+
     let routes = get_questions
+        .or(get_question)
         .or(add_question)
         .or(update_question)
+        .or(patch_question)
         .or(delete_question)
+        .or(restore_question)
+        .or(get_deleted_questions)
+        .or(get_revisions)
+        .or(get_revision)
+        .or(rollback_question)
+        .or(search)
+        .or(get_tags)
+        .or(get_tag_aliases)
+        .or(set_tag_alias)
+        .or(delete_tag_alias)
+        .or(rename_tag)
+        .or(merge_tags)
+        .or(get_answers)
+        .or(get_answer)
         .or(add_answer)
-        .recover(return_error) // 捕获 get_questions 内部或 filter 链产生的 Rejection
-        .with(cors) // 应用 CORS 策略
-        .with(log);
+        .or(update_answer)
+        .or(delete_answer);
+    // 内层把路由产生的 Rejection 转换成 problem+json，这样错误响应也会带上 CORS 头；
+    // 外层只处理 CORS 本身拒绝的请求（CorsForbidden），这时内层不会执行
+    with_problem_details(with_problem_details(routes).with(cors)) // 应用 CORS 策略
+}
 
-    warp::serve(routes)
-        .run(([127, 0, 0, 1], 3030))
-        .await;
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_routes() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+        let config = Config::parse_from(["ch06"]);
+        routes(config, Arc::new(Store::new()))
+    }
+
+    #[tokio::test]
+    async fn unknown_path_is_not_found() {
+        let routes = test_routes();
+        for (method, path) in [("GET", "/nope"), ("GET", "/questions/1/foo"), ("DELETE", "/nope"), ("POST", "/nope")] {
+            let response = warp::test::request().method(method).path(path).reply(&routes).await;
+            assert_eq!(response.status(), 404, "{} {}", method, path);
+            assert_eq!(response.headers()["content-type"], "application/problem+json");
+            let problem: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+            assert_eq!(problem["code"], "route_not_found");
+        }
+    }
+
+    #[tokio::test]
+    async fn wrong_method_on_known_path_is_not_allowed() {
+        let routes = test_routes();
+        let response = warp::test::request().method("DELETE").path("/questions").reply(&routes).await;
+        assert_eq!(response.status(), 405);
+        let response = warp::test::request().method("PATCH").path("/tags").reply(&routes).await;
+        assert_eq!(response.status(), 405);
+    }
+
+    #[tokio::test]
+    async fn missing_fields_are_reported_as_field_errors() {
+        let routes = test_routes();
+        for (path, body) in [("/questions", "{}"), ("/answers", "{\"content\": \" \"}")] {
+            let response = warp::test::request().method("POST").path(path).body(body).reply(&routes).await;
+            assert_eq!(response.status(), 422, "{}", path);
+            let problem: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+            assert_eq!(problem["code"], "validation_failed");
+            assert!(problem["errors"].as_array().unwrap().iter().all(|e| e["code"] == "required"));
+        }
+    }
+
+    #[tokio::test]
+    async fn tag_changes_require_the_admin_token() {
+        let config = Config::parse_from(["ch06", "--admin-token", "secret"]);
+        let store: SharedStore = Arc::new(Store::new());
+        store.add_question(Question {
+            id: QuestionId("1".to_string()),
+            title: "Title".to_string(),
+            content: "Content".to_string(),
+            tags: Some(vec!["warp".to_string()]),
+            metadata: crate::types::metadata::Metadata::new("alice".to_string(), chrono::Utc::now()),
+            version: 1,
+        }).await.unwrap();
+        let routes = routes(config, store.clone());
+        let requests = [
+            ("PUT", "/tags/aliases", r#"{"alias": "warp-rs", "tag": "warp"}"#),
+            ("DELETE", "/tags/aliases/warp-rs", ""),
+            ("POST", "/tags/rename", r#"{"from": "warp", "to": "web"}"#),
+            ("POST", "/tags/merge", r#"{"tags": ["web"], "into": "http"}"#),
+        ];
+        for (method, path, body) in requests {
+            let request = || warp::test::request().method(method).path(path).body(body);
+            let response = request().reply(&routes).await;
+            assert_eq!(response.status(), 401, "{} {}", method, path);
+            let response = request().header("authorization", "Bearer wrong").reply(&routes).await;
+            assert_eq!(response.status(), 403, "{} {}", method, path);
+            let response = request().header("authorization", "Bearer secret").reply(&routes).await;
+            assert!(response.status().is_success(), "{} {}: {}", method, path, response.status());
+        }
+        let question = store.get_question(&QuestionId("1".to_string())).await.unwrap();
+        assert_eq!(question.tags, Some(vec!["http".to_string()]));
+    }
+}
@@ -1,19 +1,108 @@
 use std::collections::HashMap;
-use warp::{Rejection, Reply};
-use warp::http::StatusCode;
-use crate::store::Store;
-use crate::types::answer::{Answer, AnswerId};
+use chrono::Utc;
+use warp::{Filter, Rejection, Reply};
+use warp::http::{header, StatusCode};
+use crate::ids::IdGenerator;
+use crate::store::SharedStore;
+use crate::types::answer::{Answer, AnswerId, NewAnswer, NewAnswerFields};
+use crate::routes::{json_or_form_body, paged_reply, resource_path};
+use crate::types::pagination::{
+    check_params, extract_format, extract_page_request, CursorPage, PageLimits, PageRequest, PAGE_PARAMS,
+};
 use crate::types::question::QuestionId;
+use crate::types::validation::{Validate, ValidationLimits};
 
-pub async fn add_answer(store: Store,
-                    params: HashMap<String, String>,) -> Result<impl Reply, Rejection> {
-    let answer = Answer {
-        id: AnswerId("1".to_string()),
-        content: params.get("content").unwrap().to_string(),
-        question_id: QuestionId(
-            params.get("questionId").unwrap().to_string()
-        ),
+pub async fn get_answers(question_id: String,
+                         mut params: HashMap<String, String>,
+                         limits: PageLimits,
+                         store: SharedStore) -> Result<impl Reply, Rejection> {
+    let question_id = QuestionId(question_id);
+    check_params(&params, PAGE_PARAMS).map_err(warp::reject::custom)?;
+    let format = extract_format(&mut params).map_err(warp::reject::custom)?;
+    // 问题不存在时返回 404，而不是空列表
+    store.get_question(&question_id).await.map_err(warp::reject::custom)?;
+
+    let pagination = match extract_page_request(&params, limits).map_err(warp::reject::custom)? {
+        PageRequest::Cursor(pagination) => {
+            let (items, next) = store
+                .get_answers_for_question_after(&question_id, pagination)
+                .await
+                .map_err(warp::reject::custom)?;
+            return Ok(warp::reply::json(&CursorPage::new(items, next)).into_response());
+        }
+        PageRequest::Offset(pagination) => pagination,
     };
-    store.answers.write().await.insert(answer.id.clone(), answer);
-    Ok(warp::reply::with_status("Answer added", StatusCode::OK))
-}
\ No newline at end of file
+
+    let total = store
+        .count_answers_for_question(&question_id)
+        .await
+        .map_err(warp::reject::custom)?;
+    let res: Vec<Answer> = store
+        .get_answers_for_question(&question_id, Some(pagination))
+        .await
+        .map_err(warp::reject::custom)?;
+    let path = format!("/questions/{}/answers", question_id.0);
+    Ok(paged_reply(res, total, pagination, format, &path, Vec::new()))
+}
+
+pub async fn get_answer(id: String,
+                        store: SharedStore) -> Result<impl Reply, Rejection> {
+    let answer = store
+        .get_answer(&AnswerId(id))
+        .await
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&answer))
+}
+
+// 请求体可以是 application/json 或 application/x-www-form-urlencoded
+pub fn new_answer_body(limit: u64,
+                       limits: ValidationLimits) -> impl Filter<Extract = (NewAnswer,), Error = Rejection> + Clone {
+    json_or_form_body::<NewAnswerFields>(limit)
+        .and_then(move |fields: NewAnswerFields| async move {
+            let mut answer = NewAnswer::from(fields);
+            answer.validate(&limits).map_err(warp::reject::custom)?;
+            Ok::<_, Rejection>(answer)
+        })
+}
+
+pub async fn add_answer(store: SharedStore,
+                    ids: IdGenerator,
+                    author: String,
+                    new_answer: NewAnswer,) -> Result<impl Reply, Rejection> {
+    let answer = new_answer.into_answer(AnswerId(ids.next_id()), author, Utc::now());
+    let answer = store.add_answer(answer).await.map_err(warp::reject::custom)?;
+    let location = resource_path("/answers", &answer.id.0);
+    Ok(warp::reply::with_header(
+        warp::reply::with_status(warp::reply::json(&answer), StatusCode::CREATED),
+        header::LOCATION,
+        location,
+    ))
+}
+
+pub async fn update_answer(id: String,
+                           store: SharedStore,
+                           author: String,
+                           new_answer: NewAnswer) -> Result<impl Reply, Rejection> {
+    let id = AnswerId(id);
+    // created_at 和 author 由存储层保留原来的值
+    store
+        .update_answer(&id, new_answer.into_answer(id.clone(), author, Utc::now()))
+        .await
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::with_status(
+        "Answer updated",
+        StatusCode::OK,
+    ))
+}
+
+pub async fn delete_answer(id: String,
+                           store: SharedStore) -> Result<impl Reply, Rejection> {
+    store
+        .delete_answer(&AnswerId(id))
+        .await
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::with_status(
+        "Answer deleted",
+        StatusCode::OK,
+    ))
+}
@@ -1,2 +1,289 @@
+use std::sync::Arc;
+
+use futures_util::{Stream, TryStreamExt};
+use handle_errors::{problem_response, Error};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use warp::http::header;
+use warp::http::HeaderValue;
+use warp::hyper::body::{Buf, Bytes};
+use warp::path::FullPath;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+use crate::types::etag::{EntityTags, IfMatch};
+use crate::types::media_type;
+use crate::types::metadata::ANONYMOUS;
+use crate::types::pagination::{Page, PageFormat, PageLinks, Pagination};
+use crate::types::validation::{Validate, ValidationLimits};
+
 pub mod answer;
-pub mod question;
\ No newline at end of file
+pub mod question;
+pub mod search;
+pub mod tag;
+
+// 当前请求的用户。还没有登录功能，暂时从 X-User 请求头读取，没有时为 anonymous
+pub fn author() -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("x-user").map(|user: Option<String>| {
+        user.map(|user| user.trim().to_string())
+            .filter(|user| !user.is_empty())
+            .unwrap_or_else(|| ANONYMOUS.to_string())
+    })
+}
+
+// 管理接口的权限校验，Authorization: Bearer <令牌> 必须与 --admin-token 一致；没有配置令牌时管理接口全部关闭
+pub fn admin(token: Option<String>) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    let token = Arc::new(token);
+    warp::header::optional::<String>("authorization")
+        .and_then(move |header: Option<String>| {
+            let token = token.clone();
+            async move {
+                let Some(token) = token.as_deref() else {
+                    let action = "access admin routes, no admin token is configured".to_string();
+                    return Err(warp::reject::custom(Error::Forbidden(action)));
+                };
+                match header.as_deref().and_then(|header| header.strip_prefix("Bearer ")) {
+                    None => Err(warp::reject::custom(Error::Unauthorized)),
+                    Some(given) if constant_time_eq(given.trim().as_bytes(), token.as_bytes()) => Ok(()),
+                    Some(_) => Err(warp::reject::custom(Error::Forbidden("access admin routes".to_string()))),
+                }
+            }
+        })
+        .untuple_one()
+}
+
+// 比较时间与第一个不同的字节无关，避免通过响应时间猜出令牌
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+// 客户端可以通过 X-Request-Id 传入请求 id，没有时生成一个，错误响应中会带上它
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+fn request_id() -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    warp::header::optional::<String>(REQUEST_ID_HEADER).map(|id: Option<String>| {
+        id.map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+    })
+}
+
+// 把 filter 产生的 Rejection 转换成 application/problem+json 响应，所有响应都带上 X-Request-Id
+pub fn with_problem_details<F, R>(filter: F) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone
+where
+    F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync + 'static,
+    R: Reply,
+{
+    let result = filter
+        .map(|reply: R| Ok(reply.into_response()))
+        .or_else(|r: Rejection| async move { Ok::<_, Rejection>((Err(r),)) });
+    request_id()
+        .and(warp::path::full())
+        .and(result)
+        .map(|request_id: String, path: FullPath, result: Result<Response, Rejection>| {
+            let mut response = match result {
+                Ok(response) => response,
+                Err(r) => problem_response(&r, &request_id, path.as_str()),
+            };
+            // 已经有请求 id 的响应（内层已处理过）保持不变
+            if let Ok(value) = HeaderValue::from_str(&request_id) {
+                response.headers_mut().entry(REQUEST_ID_HEADER).or_insert(value);
+            }
+            response
+        })
+}
+
+// 读取请求体，超过上限时返回 413。Content-Length 超过上限时不读取直接拒绝；
+// 分块传输的请求没有 Content-Length，边读边计数，超过上限时停止读取
+pub fn body_limit(limit: u64) -> impl Filter<Extract = (Bytes,), Error = Rejection> + Clone {
+    warp::header::optional::<u64>("content-length")
+        .and_then(move |length: Option<u64>| async move {
+            match length {
+                Some(length) if length > limit => Err(warp::reject::custom(Error::PayloadTooLarge(limit))),
+                _ => Ok(()),
+            }
+        })
+        .untuple_one()
+        .and(warp::body::stream())
+        .and_then(move |body| read_body(body, limit))
+}
+
+async fn read_body<S, B>(body: S, limit: u64) -> Result<Bytes, Rejection>
+where
+    S: Stream<Item = Result<B, warp::Error>>,
+    B: Buf,
+{
+    let mut body = std::pin::pin!(body);
+    let mut bytes = Vec::new();
+    while let Some(mut chunk) = body
+        .try_next()
+        .await
+        .map_err(|e| warp::reject::custom(Error::InvalidBody(e.to_string())))?
+    {
+        if (bytes.len() + chunk.remaining()) as u64 > limit {
+            return Err(warp::reject::custom(Error::PayloadTooLarge(limit)));
+        }
+        while chunk.has_remaining() {
+            let part = chunk.chunk();
+            let len = part.len();
+            bytes.extend_from_slice(part);
+            chunk.advance(len);
+        }
+    }
+    Ok(Bytes::from(bytes))
+}
+
+// 解析 JSON 请求体，没有 Content-Type 时也按 JSON 处理
+pub fn json_body<T>(limit: u64) -> impl Filter<Extract = (T,), Error = Rejection> + Clone
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    parsed_body(limit, false)
+}
+
+// 根据 Content-Type 解析 application/json 或 application/x-www-form-urlencoded 请求体
+pub fn json_or_form_body<T>(limit: u64) -> impl Filter<Extract = (T,), Error = Rejection> + Clone
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    parsed_body(limit, true)
+}
+
+fn parsed_body<T>(limit: u64, form: bool) -> impl Filter<Extract = (T,), Error = Rejection> + Clone
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    warp::header::optional::<String>("content-type")
+        .and(body_limit(limit))
+        .and_then(move |content_type: Option<String>, body: Bytes| async move {
+            parse_body(content_type.as_deref(), &body, form).map_err(warp::reject::custom)
+        })
+}
+
+fn parse_body<T: DeserializeOwned>(content_type: Option<&str>, body: &[u8], form: bool) -> Result<T, Error> {
+    let essence = content_type.map(media_type::essence);
+    match essence.as_deref() {
+        None | Some("application/json") => {
+            serde_json::from_slice(body).map_err(|e| Error::InvalidBody(e.to_string()))
+        }
+        Some("application/x-www-form-urlencoded") if form => {
+            serde_urlencoded::from_bytes(body).map_err(|e| Error::InvalidBody(e.to_string()))
+        }
+        Some(other) => Err(Error::UnsupportedMediaType(other.to_string())),
+    }
+}
+
+// 解析 JSON 请求体并按 Validate 中声明的规则检查，失败时返回所有字段的错误
+pub fn validated_json<T>(limit: u64, limits: ValidationLimits) -> impl Filter<Extract = (T,), Error = Rejection> + Clone
+where
+    T: DeserializeOwned + Validate + Send + 'static,
+{
+    json_body::<T>(limit).and_then(move |mut body: T| async move {
+        match body.validate(&limits) {
+            Ok(()) => Ok(body),
+            Err(e) => Err(warp::reject::custom(e)),
+        }
+    })
+}
+
+// URL 路径中的一段，除了 RFC 3986 中不需要转义的字符外都按百分号编码
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_').remove(b'.').remove(b'~');
+
+// 资源的路径，例如 resource_path("/questions", id) 得到 Location 头中的 /questions/{id}
+pub(crate) fn resource_path(collection: &str, id: &str) -> String {
+    format!("{}/{}", collection, utf8_percent_encode(id, PATH_SEGMENT))
+}
+
+pub fn if_match() -> impl Filter<Extract = (IfMatch,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("if-match")
+        .map(|header: Option<String>| IfMatch(header.as_deref().map(EntityTags::parse)))
+}
+
+pub fn if_none_match() -> impl Filter<Extract = (Option<EntityTags>,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("if-none-match")
+        .map(|header: Option<String>| header.as_deref().map(EntityTags::parse))
+}
+
+// start/end 分页的列表响应，两种格式都会带上 Link 头
+// query 是翻页时需要保留的其他参数，例如 ["sort=id"]
+pub(crate) fn paged_reply<T: Serialize>(
+    items: Vec<T>,
+    total: usize,
+    pagination: Pagination,
+    format: PageFormat,
+    path: &str,
+    mut query: Vec<String>,
+) -> Response {
+    if format == PageFormat::Array {
+        query.push("format=array".to_string());
+    }
+    let links = PageLinks::new(path, &query, pagination, total);
+    let link_header = links.header();
+    let body = match format {
+        PageFormat::Array => warp::reply::json(&items),
+        PageFormat::Envelope => warp::reply::json(&Page {
+            items,
+            total,
+            start: pagination.start,
+            limit: pagination.limit(),
+            next: links.next,
+            prev: links.prev,
+        }),
+    };
+    warp::reply::with_header(body, header::LINK, link_header).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn chunks(chunks: &[&'static str]) -> impl Stream<Item = Result<Bytes, warp::Error>> {
+        futures_util::stream::iter(chunks.iter().map(|chunk| Ok(Bytes::from_static(chunk.as_bytes()))).collect::<Vec<_>>())
+    }
+
+    #[tokio::test]
+    async fn read_body_counts_chunks_against_limit() {
+        let body = read_body(chunks(&["{\"a\":", "\"1\"}"]), 9).await.unwrap();
+        assert_eq!(&body[..], b"{\"a\":\"1\"}");
+
+        let rejection = read_body(chunks(&["{\"a\":", "\"1\"}"]), 8).await.unwrap_err();
+        assert!(matches!(rejection.find::<Error>(), Some(Error::PayloadTooLarge(8))));
+    }
+
+    #[tokio::test]
+    async fn body_limit_checks_content_length() {
+        let filter = body_limit(16);
+        let body = warp::test::request().body("{}").filter(&filter).await.unwrap();
+        assert_eq!(&body[..], b"{}");
+
+        let rejection = warp::test::request().body("x".repeat(17)).filter(&filter).await.unwrap_err();
+        assert!(matches!(rejection.find::<Error>(), Some(Error::PayloadTooLarge(16))));
+    }
+
+    #[test]
+    fn resource_path_encodes_the_id() {
+        assert_eq!(resource_path("/questions", "q-1_A.b~"), "/questions/q-1_A.b~");
+        assert_eq!(resource_path("/answers", "a/b?c#d e"), "/answers/a%2Fb%3Fc%23d%20e");
+    }
+
+    #[test]
+    fn parse_body_dispatches_on_content_type() {
+        let expected = HashMap::from([("content".to_string(), "hi".to_string())]);
+        let json: HashMap<String, String> = parse_body(None, b"{\"content\":\"hi\"}", false).unwrap();
+        assert_eq!(json, expected);
+        let json: HashMap<String, String> =
+            parse_body(Some("Application/JSON; charset=utf-8"), b"{\"content\":\"hi\"}", false).unwrap();
+        assert_eq!(json, expected);
+        let form: HashMap<String, String> =
+            parse_body(Some("application/x-www-form-urlencoded"), b"content=hi", true).unwrap();
+        assert_eq!(form, expected);
+
+        let form = parse_body::<HashMap<String, String>>(Some("application/x-www-form-urlencoded"), b"content=hi", false);
+        assert!(matches!(form, Err(Error::UnsupportedMediaType(_))));
+        let invalid = parse_body::<HashMap<String, String>>(None, b"{", false);
+        assert!(matches!(invalid, Err(Error::InvalidBody(_))));
+    }
+}
@@ -1,83 +1,261 @@
-use std::cmp;
 use std::collections::HashMap;
+use chrono::Utc;
 use handle_errors::Error;
+use serde::{Deserialize, Serialize};
 use warp::{Rejection, Reply};
-use warp::http::StatusCode;
-use crate::store;
-use crate::store::Store;
-use crate::types::pagination::extract_pagination;
-use crate::types::question::{Question, QuestionId};
+use warp::http::{header, StatusCode};
+use warp::hyper::body::Bytes;
+use crate::ids::IdGenerator;
+use crate::store::SharedStore;
+use crate::types::answer::Answer;
+use crate::types::etag::{etag, EntityTags, IfMatch};
+use crate::routes::{paged_reply, resource_path};
+use crate::types::pagination::{
+    check_params, extract_format, extract_page_request, extract_sort, CursorPage, PageLimits, PageRequest,
+    SortOrder, PAGE_PARAMS,
+};
+use crate::types::patch::PatchFormat;
+use crate::types::question::{NewQuestion, Question, QuestionId, QuestionUpdate};
+use crate::types::revision::Edit;
+use crate::types::tag::extract_tag_filter;
+use crate::types::validation::{Validate, ValidationLimits};
 
-pub async fn get_questions(params: HashMap<String, String>,store: store::Store, id: String,) -> Result<impl Reply, Rejection> {
+#[derive(Debug, Deserialize)]
+pub struct GetQuestionParams {
+    // 逗号分隔，目前只支持 answers
+    include: Option<String>,
+}
+
+impl GetQuestionParams {
+    fn includes(&self, name: &str) -> bool {
+        self.include
+            .as_deref()
+            .is_some_and(|include| include.split(',').any(|part| part.trim() == name))
+    }
+}
+
+#[derive(Serialize)]
+struct QuestionWithAnswers {
+    #[serde(flatten)]
+    question: Question,
+    answers: Vec<Answer>,
+}
+
+pub async fn get_questions(mut query: Vec<(String, String)>,
+                           limits: PageLimits,
+                           store: SharedStore,
+                           id: String,) -> Result<impl Reply, Rejection> {
     log::info!("Start querying question");
-    if params.is_empty() {
-        // 没有查询参数，返回所有问题
-        let res: Vec<Question> = store.questions.read().await.values().cloned().collect();
-        Ok(warp::reply::json(&res))
-    } else {
-        // 有查询参数，尝试提取分页信息
-        match extract_pagination(params) {
-            Ok(pagination) => {
-                log::info!("{} Pagination set {:?}", id, &pagination);
-                let all_questions: Vec<Question> = store.questions.read().await.values().cloned().collect();
-                let total_len = all_questions.len();
-
-                // 确保 start 和 end 不会越界
-                let start = cmp::min(pagination.start, total_len);
-                let end = cmp::min(pagination.end, total_len);
-
-                // 确保 start <= end，如果 start > end，返回空结果
-                if start >= end {
-                    let empty_questions: Vec<Question> = Vec::new();
-                    Ok(warp::reply::json(&empty_questions))
-                } else {
-                    // 安全地进行切片
-                    let paginated_questions = &all_questions[start..end];
-                    Ok(warp::reply::json(&paginated_questions))
-                }
-            },
-            Err(e) => {
-                log::info!("{} No Pagination used", id);
-                // 如果提取分页参数失败，返回一个 Rejection
-                Err(warp::reject::custom(e))
-            }
+    // tag 可以重复出现，先取出来，其余参数按名字处理
+    let tags = extract_tag_filter(&mut query).map_err(warp::reject::custom)?;
+    let tags = tags.as_ref();
+    let mut params: HashMap<String, String> = query.into_iter().collect();
+    let allowed: Vec<&str> = PAGE_PARAMS.iter().copied().chain(["sort"]).collect();
+    check_params(&params, &allowed).map_err(warp::reject::custom)?;
+    let sort = extract_sort(&mut params).map_err(warp::reject::custom)?;
+    let format = extract_format(&mut params).map_err(warp::reject::custom)?;
+
+    let pagination = match extract_page_request(&params, limits).map_err(warp::reject::custom)? {
+        PageRequest::Cursor(pagination) => {
+            // 游标分页，返回 { items, next_cursor }
+            log::info!("{} Cursor pagination set {:?}", id, &pagination);
+            let (items, next) = store
+                .get_questions_after(sort, tags, pagination)
+                .await
+                .map_err(warp::reject::custom)?;
+            return Ok(warp::reply::json(&CursorPage::new(items, next)).into_response());
         }
+        PageRequest::Offset(pagination) => pagination,
+    };
+    log::info!("{} Pagination set {:?}", id, &pagination);
+
+    let total = store.count_questions(tags).await.map_err(warp::reject::custom)?;
+    // 越界的情况由存储层处理，返回空结果
+    let res: Vec<Question> = store
+        .get_questions(sort, tags, Some(pagination))
+        .await
+        .map_err(warp::reject::custom)?;
+
+    let mut query = Vec::new();
+    if sort != SortOrder::default() {
+        query.push(format!("sort={}", sort.as_str()));
     }
+    if let Some(filter) = tags {
+        query.extend(filter.query());
+    }
+    Ok(paged_reply(res, total, pagination, format, "/questions", query))
 }
 
-pub async fn add_question(store: Store,
-                      question: Question) -> Result<impl Reply, Rejection> {
-    store.questions.write().await.insert(question.id.clone(), question);
-    Ok(warp::reply::with_status(
-        "Question added",
-        StatusCode::OK,
+pub async fn get_question(id: String,
+                      params: GetQuestionParams,
+                      if_none_match: Option<EntityTags>,
+                      store: SharedStore) -> Result<impl Reply, Rejection> {
+    let question = store
+        .get_question(&QuestionId(id))
+        .await
+        .map_err(warp::reject::custom)?;
+
+    if params.includes("answers") {
+        // 响应中还包含回答，问题的版本号不能代表整个响应，不返回 ETag
+        let answers = store
+            .get_answers_for_question(&question.id, None)
+            .await
+            .map_err(warp::reject::custom)?;
+        return Ok(warp::reply::json(&QuestionWithAnswers { question, answers }).into_response());
+    }
+
+    let etag = etag(question.version);
+    if if_none_match.is_some_and(|tags| tags.matches_weak(question.version)) {
+        return Ok(warp::reply::with_header(StatusCode::NOT_MODIFIED, header::ETAG, etag).into_response());
+    }
+    Ok(warp::reply::with_header(warp::reply::json(&question), header::ETAG, etag).into_response())
+}
+
+pub async fn add_question(store: SharedStore,
+                      ids: IdGenerator,
+                      author: String,
+                      new_question: NewQuestion) -> Result<impl Reply, Rejection> {
+    let id = ids.question_id(new_question.id.clone());
+    let question = store
+        .add_question(new_question.into_question(id, author, Utc::now()))
+        .await
+        .map_err(warp::reject::custom)?;
+    let location = resource_path("/questions", &question.id.0);
+    Ok(warp::reply::with_header(
+        warp::reply::with_status(warp::reply::json(&question), StatusCode::CREATED),
+        header::LOCATION,
+        location,
     ))
 }
 
+// 带 If-Match 时只有版本号匹配才会修改，否则返回 412
 pub async fn update_question(id: String,
-                         store: Store,
-                         question: Question) -> Result<impl Reply, Rejection> {
-    match store.questions.write().await.get_mut(&QuestionId(id)) {
-        Some(q) => *q = question,
-        None => return Err(warp::reject::custom(Error::QuestionNotFound)),
+                         store: SharedStore,
+                         editor: String,
+                         if_match: IfMatch,
+                         update: QuestionUpdate) -> Result<impl Reply, Rejection> {
+    let mut question = update.question;
+    if question.id.0 != id {
+        return Err(warp::reject::custom(Error::IdMismatch(id, question.id.0)));
     }
+    // created_at、author 和 version 由存储层维护
+    question.metadata.updated_at = Utc::now();
+    let edit = Edit { editor, summary: update.summary };
+    let question = store
+        .update_question(&QuestionId(id), question, &edit, &if_match)
+        .await
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::with_header(
+        warp::reply::with_status("Question updated", StatusCode::OK),
+        header::ETAG,
+        etag(question.version),
+    ))
+}
+
+// 部分修改，Content-Type 决定请求体按 JSON Merge Patch 还是 JSON Patch 处理
+pub async fn patch_question(id: String,
+                        store: SharedStore,
+                        editor: String,
+                        if_match: IfMatch,
+                        limits: ValidationLimits,
+                        content_type: Option<String>,
+                        body: Bytes) -> Result<impl Reply, Rejection> {
+    let format = PatchFormat::from_content_type(content_type.as_deref().unwrap_or_default())
+        .map_err(warp::reject::custom)?;
+    let id = QuestionId(id);
+    let current = store.get_question(&id).await.map_err(warp::reject::custom)?;
+    if_match.check(current.version).map_err(warp::reject::custom)?;
+    let mut question = format.apply(&current, &body).map_err(warp::reject::custom)?;
+    question.validate(&limits).map_err(warp::reject::custom)?;
+    question.metadata.updated_at = Utc::now();
+    let edit = Edit { editor, summary: None };
+    // 补丁基于上面读取到的版本，期间被其他请求修改时不会覆盖别人的修改
+    let question = store
+        .update_question(&id, question, &edit, &IfMatch::version(current.version))
+        .await
+        .map_err(|e| match e {
+            // 客户端没有带 If-Match，版本号是这里读取的
+            Error::PreconditionFailed => if_match.modified(),
+            e => e,
+        })
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::with_header(
+        warp::reply::json(&question),
+        header::ETAG,
+        etag(question.version),
+    ))
+}
+
+pub async fn get_revisions(id: String,
+                         store: SharedStore) -> Result<impl Reply, Rejection> {
+    let revisions = store
+        .get_revisions(&QuestionId(id))
+        .await
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&revisions))
+}
+
+pub async fn get_revision(id: String,
+                        number: u64,
+                        store: SharedStore) -> Result<impl Reply, Rejection> {
+    let revision = store
+        .get_revision(&QuestionId(id), number)
+        .await
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&revision))
+}
+
+// 用历史版本的内容再做一次修改，回滚本身也会记录为一个新版本
+pub async fn rollback_question(id: String,
+                             number: u64,
+                             store: SharedStore,
+                             editor: String) -> Result<impl Reply, Rejection> {
+    let id = QuestionId(id);
+    let revision = store.get_revision(&id, number).await.map_err(warp::reject::custom)?;
+    let mut question = store.get_question(&id).await.map_err(warp::reject::custom)?;
+    question.title = revision.title;
+    question.content = revision.content;
+    question.tags = revision.tags;
+    question.metadata.updated_at = Utc::now();
+    let edit = Edit {
+        editor,
+        summary: Some(format!("Rolled back to revision {}", number)),
+    };
+    let question = store
+        .update_question(&id, question, &edit, &IfMatch::default())
+        .await
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&question))
+}
+
+// 问题移到回收站，保留期内可以通过 POST /questions/{id}/restore 恢复
+pub async fn delete_question(id: String,
+                         store: SharedStore,
+                         deleted_by: String,
+                         if_match: IfMatch) -> Result<impl Reply, Rejection> {
+    store
+        .delete_question(&QuestionId(id), &deleted_by, &if_match)
+        .await
+        .map_err(warp::reject::custom)?;
     Ok(warp::reply::with_status(
-        "Question updated",
+        "Question deleted",
         StatusCode::OK,
     ))
 }
 
-pub async fn delete_question(id: String,
-                         store: Store) -> Result<impl Reply, Rejection> {
-    match store.questions.write().await.remove(&QuestionId(id)) {
-        Some(_) => {
-            return Ok(
-                warp::reply::with_status(
-                    "Question deleted",
-                    StatusCode::OK,
-                )
-            )
-        },
-        None => Err(warp::reject::custom(Error::QuestionNotFound))
-    }
-}
\ No newline at end of file
+pub async fn restore_question(id: String,
+                              store: SharedStore) -> Result<impl Reply, Rejection> {
+    let question = store
+        .restore_question(&QuestionId(id))
+        .await
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&question))
+}
+
+pub async fn get_deleted_questions(store: SharedStore) -> Result<impl Reply, Rejection> {
+    let questions = store
+        .get_deleted_questions()
+        .await
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&questions))
+}
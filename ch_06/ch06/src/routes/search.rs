@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use serde::Serialize;
+use warp::{Rejection, Reply};
+use handle_errors::Error;
+use crate::routes::paged_reply;
+use crate::search::{highlight, query_terms};
+use crate::store::SharedStore;
+use crate::types::pagination::{
+    check_params, extract_format, extract_page_request, PageLimits, PageRequest, Pagination, PAGE_PARAMS,
+};
+use crate::types::question::Question;
+
+#[derive(Serialize)]
+struct SearchHit {
+    #[serde(flatten)]
+    question: Question,
+    score: f64,
+    // 命中词附近的一段内容，已转义为 HTML，命中的词用 <mark></mark> 标出
+    snippet: String,
+}
+
+pub async fn search_questions(mut params: HashMap<String, String>,
+                              limits: PageLimits,
+                              store: SharedStore) -> Result<impl Reply, Rejection> {
+    let allowed: Vec<&str> = PAGE_PARAMS.iter().copied().filter(|key| *key != "cursor").chain(["q"]).collect();
+    check_params(&params, &allowed).map_err(warp::reject::custom)?;
+    let format = extract_format(&mut params).map_err(warp::reject::custom)?;
+    let query = match params.remove("q") {
+        Some(query) if !query.trim().is_empty() => query,
+        _ => return Err(warp::reject::custom(Error::MissingSearchQuery)),
+    };
+
+    let pagination = match extract_page_request(&params, limits).map_err(warp::reject::custom)? {
+        PageRequest::Offset(pagination) => pagination,
+        // 结果按相关度排序，不支持游标，cursor 参数已经被 check_params 拒绝
+        PageRequest::Cursor(pagination) => Pagination { start: 0, end: pagination.limit },
+    };
+
+    let (results, total) = store
+        .search_questions(&query, pagination)
+        .await
+        .map_err(warp::reject::custom)?;
+
+    let terms = query_terms(&query);
+    let hits: Vec<SearchHit> = results
+        .into_iter()
+        .map(|(question, score)| {
+            // 优先使用内容中的命中，只有标题命中时用标题
+            let snippet = highlight(&question.content, &terms)
+                .or_else(|| highlight(&question.title, &terms))
+                .unwrap_or_default();
+            SearchHit { question, score, snippet }
+        })
+        .collect();
+
+    let query = serde_urlencoded::to_string([("q", &query)]).unwrap_or_default();
+    Ok(paged_reply(hits, total, pagination, format, "/search", vec![query]))
+}
@@ -0,0 +1,67 @@
+use serde::Serialize;
+use warp::{Rejection, Reply};
+use warp::http::StatusCode;
+use percent_encoding::percent_decode_str;
+use crate::store::SharedStore;
+use crate::types::tag::{MergeTags, RenameTag, TagAlias};
+
+#[derive(Serialize)]
+struct TagsRenamed {
+    // 标签被修改的问题数
+    updated: usize,
+}
+
+pub async fn get_tags(store: SharedStore) -> Result<impl Reply, Rejection> {
+    let tags = store.get_tags().await.map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&tags))
+}
+
+pub async fn get_tag_aliases(store: SharedStore) -> Result<impl Reply, Rejection> {
+    let aliases = store.get_tag_aliases().await.map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&aliases))
+}
+
+pub async fn set_tag_alias(store: SharedStore,
+                           author: String,
+                           alias: TagAlias) -> Result<impl Reply, Rejection> {
+    let alias = store
+        .set_tag_alias(&alias.alias, &alias.tag, &author)
+        .await
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&alias))
+}
+
+pub async fn delete_tag_alias(alias: String,
+                              store: SharedStore) -> Result<impl Reply, Rejection> {
+    // 路径参数不会自动解码，别名中可能有空格等字符
+    let alias = percent_decode_str(&alias).decode_utf8_lossy();
+    store
+        .delete_tag_alias(&alias)
+        .await
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::with_status(
+        "Tag alias deleted",
+        StatusCode::OK,
+    ))
+}
+
+pub async fn rename_tag(store: SharedStore,
+                        author: String,
+                        rename: RenameTag) -> Result<impl Reply, Rejection> {
+    let updated = store
+        .rename_tag(&rename.from, &rename.to, &author)
+        .await
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&TagsRenamed { updated }))
+}
+
+// 把每个标签改名为 into，没有被使用的标签直接跳过
+pub async fn merge_tags(store: SharedStore,
+                        author: String,
+                        merge: MergeTags) -> Result<impl Reply, Rejection> {
+    let updated = store
+        .merge_tags(&merge.tags, &merge.into, &author)
+        .await
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&TagsRenamed { updated }))
+}
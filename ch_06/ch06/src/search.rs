@@ -0,0 +1,276 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+
+use rust_stemmers::{Algorithm, Stemmer};
+
+use crate::{Question, QuestionId};
+
+// BM25 参数，取常用的默认值
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+// 摘要包含的词数，以及第一个命中词之前保留的词数
+const SNIPPET_WORDS: usize = 30;
+const SNIPPET_LEAD: usize = 5;
+
+static STEMMER: LazyLock<Stemmer> = LazyLock::new(|| Stemmer::create(Algorithm::English));
+
+// 问题标题和内容的倒排索引，存储层在增删改问题时同步更新
+#[derive(Default)]
+pub struct SearchIndex {
+    // 词项 → 包含该词的问题及词频
+    postings: HashMap<String, HashMap<QuestionId, u32>>,
+    // 每个问题的词项（去重）和总词数，删除和计算 BM25 时使用
+    docs: HashMap<QuestionId, Document>,
+    total_len: usize,
+}
+
+struct Document {
+    terms: Vec<String>,
+    len: usize,
+}
+
+impl SearchIndex {
+    pub fn build<'a>(questions: impl IntoIterator<Item = (&'a QuestionId, &'a Question)>) -> Self {
+        let mut index = SearchIndex::default();
+        for (id, question) in questions {
+            index.insert(id, question);
+        }
+        index
+    }
+
+    // 已经索引过的问题先删除旧的词项，再按新内容重新索引
+    pub fn insert(&mut self, id: &QuestionId, question: &Question) {
+        self.remove(id);
+
+        let mut freqs: HashMap<String, u32> = HashMap::new();
+        let mut len = 0;
+        for text in [&question.title, &question.content] {
+            for (_, term) in tokenize(text) {
+                *freqs.entry(term).or_default() += 1;
+                len += 1;
+            }
+        }
+
+        let mut terms = Vec::with_capacity(freqs.len());
+        for (term, freq) in freqs {
+            self.postings.entry(term.clone()).or_default().insert(id.clone(), freq);
+            terms.push(term);
+        }
+        self.total_len += len;
+        self.docs.insert(id.clone(), Document { terms, len });
+    }
+
+    pub fn remove(&mut self, id: &QuestionId) {
+        let Some(doc) = self.docs.remove(id) else {
+            return;
+        };
+        self.total_len -= doc.len;
+        for term in doc.terms {
+            if let Some(ids) = self.postings.get_mut(&term) {
+                ids.remove(id);
+                if ids.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+    }
+
+    // 包含任意一个查询词的问题，按 BM25 得分从高到低排序，得分相同时按 id 排序
+    pub fn search(&self, query: &str) -> Vec<(QuestionId, f64)> {
+        if self.docs.is_empty() {
+            return Vec::new();
+        }
+        let count = self.docs.len() as f64;
+        let avg_len = self.total_len as f64 / count;
+
+        let mut scores: HashMap<&QuestionId, f64> = HashMap::new();
+        for term in query_terms(query) {
+            let Some(ids) = self.postings.get(&term) else {
+                continue;
+            };
+            let matched = ids.len() as f64;
+            let idf = (1.0 + (count - matched + 0.5) / (matched + 0.5)).ln();
+            for (id, freq) in ids {
+                let len = self.docs.get(id).map_or(0, |doc| doc.len) as f64;
+                let freq = *freq as f64;
+                let norm = K1 * (1.0 - B + B * len / avg_len.max(1.0));
+                *scores.entry(id).or_default() += idf * freq * (K1 + 1.0) / (freq + norm);
+            }
+        }
+
+        let mut results: Vec<(QuestionId, f64)> =
+            scores.into_iter().map(|(id, score)| (id.clone(), score)).collect();
+        results.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        results
+    }
+}
+
+// 按非字母数字字符切分，转成小写后取英文词干；返回每个词在原文中的字节范围
+pub fn tokenize(text: &str) -> Vec<((usize, usize), String)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                let word = text[s..i].to_lowercase();
+                tokens.push(((s, i), STEMMER.stem(&word).into_owned()));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    tokens
+}
+
+pub fn query_terms(query: &str) -> HashSet<String> {
+    tokenize(query).into_iter().map(|(_, term)| term).collect()
+}
+
+// 截取第一个命中词附近的一段文字，命中的词用 <mark></mark> 包起来；没有命中时返回 None
+// 结果作为 HTML 显示，原文中的 HTML 特殊字符全部转义，只有 <mark> 是标签
+pub fn highlight(text: &str, terms: &HashSet<String>) -> Option<String> {
+    let tokens = tokenize(text);
+    let first = tokens.iter().position(|(_, term)| terms.contains(term))?;
+    let from = first.saturating_sub(SNIPPET_LEAD);
+    let to = (from + SNIPPET_WORDS).min(tokens.len());
+
+    let mut snippet = String::new();
+    if from > 0 {
+        snippet.push('…');
+    }
+    let mut pos = tokens[from].0 .0;
+    for ((start, end), term) in &tokens[from..to] {
+        escape_html(&text[pos..*start], &mut snippet);
+        match terms.contains(term) {
+            true => {
+                snippet.push_str("<mark>");
+                escape_html(&text[*start..*end], &mut snippet);
+                snippet.push_str("</mark>");
+            }
+            false => escape_html(&text[*start..*end], &mut snippet),
+        }
+        pos = *end;
+    }
+    if to < tokens.len() {
+        snippet.push('…');
+    }
+    Some(snippet)
+}
+
+fn escape_html(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::types::metadata::Metadata;
+
+    fn question(id: &str, title: &str, content: &str) -> (QuestionId, Question) {
+        let id = QuestionId(id.to_string());
+        let question = Question {
+            id: id.clone(),
+            title: title.to_string(),
+            content: content.to_string(),
+            tags: None,
+            metadata: Metadata::new("alice".to_string(), Utc::now()),
+            version: 1,
+        };
+        (id, question)
+    }
+
+    fn terms(query: &str) -> HashSet<String> {
+        query_terms(query)
+    }
+
+    fn ids(results: &[(QuestionId, f64)]) -> Vec<&str> {
+        results.iter().map(|(id, _)| id.0.as_str()).collect()
+    }
+
+    #[test]
+    fn tokenize_stems_and_keeps_utf8_byte_ranges() {
+        let text = "Naïve café-Filters running";
+        let tokens = tokenize(text);
+        let words: Vec<&str> = tokens.iter().map(|((s, e), _)| &text[*s..*e]).collect();
+        assert_eq!(words, ["Naïve", "café", "Filters", "running"]);
+        let stems: Vec<&str> = tokens.iter().map(|(_, term)| term.as_str()).collect();
+        assert_eq!(stems, ["naïv", "café", "filter", "run"]);
+        assert_eq!(terms("filter FILTERS filtered"), HashSet::from(["filter".to_string()]));
+    }
+
+    #[test]
+    fn bm25_prefers_frequent_terms_in_short_documents() {
+        let questions = [
+            question("1", "Warp", "filters filters filters"),
+            question("2", "Warp", "filters and a lot of other words about routing and handlers"),
+            question("3", "Tokio", "runtime"),
+        ];
+        let index = SearchIndex::build(questions.iter().map(|(id, q)| (id, q)));
+        let results = index.search("filter");
+        assert_eq!(ids(&results), ["1", "2"]);
+        assert!(results[0].1 > results[1].1);
+
+        // 出现在较少问题中的词 idf 更高
+        let results = index.search("warp runtime");
+        assert_eq!(ids(&results), ["3", "1", "2"]);
+        assert!(index.search("missing").is_empty());
+        assert!(SearchIndex::default().search("warp").is_empty());
+    }
+
+    #[test]
+    fn equal_scores_are_ordered_by_id() {
+        let questions = [question("b", "warp", ""), question("a", "warp", ""), question("c", "warp", "")];
+        let index = SearchIndex::build(questions.iter().map(|(id, q)| (id, q)));
+        assert_eq!(ids(&index.search("warp")), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn reindexing_replaces_old_terms() {
+        let (id, mut q) = question("1", "Warp filters", "");
+        let mut index = SearchIndex::build([(&id, &q)]);
+        q.title = "Tokio runtime".to_string();
+        index.insert(&id, &q);
+        assert!(index.search("warp").is_empty());
+        assert_eq!(ids(&index.search("tokio")), ["1"]);
+        assert_eq!(index.total_len, 2);
+
+        index.remove(&id);
+        assert!(index.search("tokio").is_empty());
+        assert_eq!((index.total_len, index.postings.len()), (0, 0));
+    }
+
+    #[test]
+    fn highlight_escapes_html_around_marks() {
+        let snippet = highlight("Warp <b>filters</b> & \"quotes\" 'x'", &terms("filter")).unwrap();
+        assert_eq!(snippet, "Warp &lt;b&gt;<mark>filters</mark>&lt;/b&gt; &amp; &quot;quotes&quot; &#39;x");
+        let snippet = highlight("<script>alert(1)</script>", &terms("alert")).unwrap();
+        assert!(!snippet.contains("<script>"));
+        assert_eq!(highlight("nothing here", &terms("filter")), None);
+    }
+
+    #[test]
+    fn highlight_windows_long_text_on_char_boundaries() {
+        let words: Vec<String> = (0..60).map(|i| format!("wörd{}é", i)).collect();
+        let mut text = words.join(" — ");
+        text.push_str(" — filters");
+        let snippet = highlight(&text, &terms("filter")).unwrap();
+        assert!(snippet.starts_with("…wörd55é — "));
+        assert!(snippet.ends_with("<mark>filters</mark>"));
+
+        let snippet = highlight(&format!("filters {}", words.join(" ")), &terms("filter")).unwrap();
+        assert!(snippet.starts_with("<mark>filters</mark> wörd0é"));
+        assert!(snippet.ends_with("wörd28é…"));
+    }
+}
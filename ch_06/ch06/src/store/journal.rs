@@ -0,0 +1,169 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use handle_errors::Error;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+const JOURNAL_FILE: &str = "journal.log";
+const QUESTIONS_FILE: &str = "questions.json";
+const STATE_FILE: &str = "state.json";
+
+// 数据目录中的文件：
+// questions.json  问题快照，与 question.json 格式相同，可以直接作为 --seed 使用
+// state.json      其余数据（回答等）的快照
+// journal.log     上次快照之后的每一次修改，一行一个 JSON
+pub struct Journal {
+    dir: PathBuf,
+    file: Mutex<File>,
+}
+
+impl Journal {
+    pub fn open(dir: &Path) -> Result<Self, Error> {
+        fs::create_dir_all(dir).map_err(Error::JournalError)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(JOURNAL_FILE))
+            .map_err(Error::JournalError)?;
+        Ok(Journal {
+            dir: dir.to_path_buf(),
+            file: Mutex::new(file),
+        })
+    }
+
+    // 读取快照，文件不存在时返回 None
+    pub fn load_snapshot<Q, S>(&self) -> Result<Option<(Q, S)>, Error>
+    where
+        Q: DeserializeOwned,
+        S: DeserializeOwned + Default,
+    {
+        let questions = match read_json::<Q>(&self.dir.join(QUESTIONS_FILE))? {
+            Some(questions) => questions,
+            None => return Ok(None),
+        };
+        let state = read_json::<S>(&self.dir.join(STATE_FILE))?.unwrap_or_default();
+        Ok(Some((questions, state)))
+    }
+
+    // 读取快照之后记录的修改；进程崩溃时最后一行可能只写了一半，直接丢弃
+    pub fn replay<E: DeserializeOwned>(&self) -> Result<Vec<E>, Error> {
+        let file = File::open(self.dir.join(JOURNAL_FILE)).map_err(Error::JournalError)?;
+        let lines = BufReader::new(file)
+            .lines()
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(Error::JournalError)?;
+
+        let mut events = Vec::with_capacity(lines.len());
+        for (i, line) in lines.iter().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(line) {
+                Ok(event) => events.push(event),
+                Err(e) if i + 1 == lines.len() => {
+                    log::warn!("Discarding incomplete journal entry: {}", e);
+                }
+                Err(e) => return Err(Error::JournalError(e.into())),
+            }
+        }
+        Ok(events)
+    }
+
+    // 先写日志再修改内存，保证写入成功的修改在重启后都能恢复
+    // 这里只有阻塞的文件 I/O，序列化由调用方完成（见 encode_event），调用方应放到 spawn_blocking 中执行
+    pub fn append(&self, line: &str) -> Result<(), Error> {
+        let mut file = self.file.lock().unwrap();
+        file.write_all(line.as_bytes()).map_err(Error::JournalError)?;
+        file.sync_data().map_err(Error::JournalError)
+    }
+
+    // 把序列化好的快照写入文件并清空日志，调用方需要保证期间没有新的修改
+    pub fn compact(&self, questions: &str, state: &str) -> Result<(), Error> {
+        write_file(&self.dir.join(QUESTIONS_FILE), questions)?;
+        write_file(&self.dir.join(STATE_FILE), state)?;
+
+        let file = self.file.lock().unwrap();
+        file.set_len(0).map_err(Error::JournalError)?;
+        file.sync_data().map_err(Error::JournalError)
+    }
+}
+
+// 日志中的一行，末尾带换行
+pub fn encode_event<E: Serialize>(event: &E) -> Result<String, Error> {
+    let mut line = serde_json::to_string(event).map_err(|e| Error::JournalError(e.into()))?;
+    line.push('\n');
+    Ok(line)
+}
+
+pub fn encode_snapshot<T: Serialize>(value: &T) -> Result<String, Error> {
+    serde_json::to_string_pretty(value).map_err(|e| Error::JournalError(e.into()))
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, Error> {
+    match fs::read_to_string(path) {
+        Ok(file) => serde_json::from_str(&file)
+            .map(Some)
+            .map_err(|e| Error::JournalError(e.into())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(Error::JournalError(e)),
+    }
+}
+
+// 先写临时文件再重命名，避免崩溃时留下半个快照
+fn write_file(path: &Path, json: &str) -> Result<(), Error> {
+    let tmp = path.with_extension("json.tmp");
+    let mut file = File::create(&tmp).map_err(Error::JournalError)?;
+    file.write_all(json.as_bytes()).map_err(Error::JournalError)?;
+    file.sync_all().map_err(Error::JournalError)?;
+    fs::rename(&tmp, path).map_err(Error::JournalError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn open() -> (PathBuf, Journal) {
+        let dir = std::env::temp_dir().join(format!("ch06-journal-{}", uuid::Uuid::new_v4()));
+        let journal = Journal::open(&dir).unwrap();
+        (dir, journal)
+    }
+
+    #[test]
+    fn replay_discards_torn_last_line() {
+        let (dir, journal) = open();
+        journal.append(&encode_event(&json!({"op": "a"})).unwrap()).unwrap();
+        journal.append(&encode_event(&json!({"op": "b"})).unwrap()).unwrap();
+        journal.append(r#"{"op": "#).unwrap();
+
+        let events = journal.replay::<Value>().unwrap();
+        assert_eq!(events, vec![json!({"op": "a"}), json!({"op": "b"})]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn replay_rejects_corrupt_line_in_the_middle() {
+        let (dir, journal) = open();
+        journal.append("not json\n").unwrap();
+        journal.append(&encode_event(&json!({"op": "a"})).unwrap()).unwrap();
+
+        assert!(matches!(journal.replay::<Value>(), Err(Error::JournalError(_))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn compact_replaces_snapshot_and_truncates_journal() {
+        let (dir, journal) = open();
+        journal.append(&encode_event(&json!({"op": "a"})).unwrap()).unwrap();
+        journal.compact(r#"{"1": 1}"#, r#"{"next_seq": 2}"#).unwrap();
+
+        let (questions, state) = journal.load_snapshot::<Value, Value>().unwrap().unwrap();
+        assert_eq!(questions, json!({"1": 1}));
+        assert_eq!(state, json!({"next_seq": 2}));
+        assert!(journal.replay::<Value>().unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
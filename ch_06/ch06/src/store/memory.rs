@@ -0,0 +1,1141 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Bound::{Excluded, Unbounded};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use handle_errors::Error;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::search::SearchIndex;
+use crate::store::journal::{encode_event, encode_snapshot, Journal};
+use crate::store::{keyset_page, paginate, AnswerRepository, QuestionRepository, TagRepository};
+use crate::types::etag::IfMatch;
+use crate::types::pagination::{Cursor, CursorPagination, Pagination, SortOrder};
+use crate::types::question::DeletedQuestion;
+use crate::types::revision::{Edit, Revision};
+use crate::types::tag::{merge_map, required_tag, resolve_tags, TagAlias, TagCount, TagFilter, TagMatch};
+use crate::{Answer, AnswerId, Question, QuestionId};
+
+#[derive(Clone)]
+pub struct Store {
+    state: Arc<RwLock<State>>,
+    journal: Option<Arc<Journal>>,
+}
+
+// questions 单独保存为 question.json 格式的快照，其余字段保存在 state.json
+#[derive(Default, Serialize, Deserialize)]
+struct State {
+    // BTreeMap 本身就是按 id 排序的索引
+    #[serde(skip)]
+    questions: BTreeMap<QuestionId, Question>,
+    // 按创建顺序排列的问题 id，key 是递增的序号
+    created: BTreeMap<u64, QuestionId>,
+    next_seq: u64,
+    #[serde(skip)]
+    created_seq: HashMap<QuestionId, u64>,
+    answers: HashMap<AnswerId, Answer>,
+    // 每个问题下的回答 id，启动时根据 answers 重建
+    #[serde(skip)]
+    question_answers: BTreeMap<QuestionId, BTreeSet<AnswerId>>,
+    // 标签 → 问题 id，按标签过滤时不需要遍历所有问题，启动时根据 questions 重建
+    #[serde(skip)]
+    tagged: HashMap<String, BTreeSet<QuestionId>>,
+    // 标题和内容的全文索引，启动时根据 questions 重建
+    #[serde(skip)]
+    search: SearchIndex,
+    // 标签别名 → 标签，别名不会指向另一个别名
+    #[serde(default)]
+    aliases: BTreeMap<String, String>,
+    // 每个问题的历史版本，按版本号保存，重复重放日志时直接覆盖
+    #[serde(default)]
+    revisions: HashMap<QuestionId, BTreeMap<u64, Revision>>,
+    // 回收站，问题和它的回答从上面的索引中移出来单独保存，恢复时放回原来的创建顺序
+    #[serde(default)]
+    trash: BTreeMap<QuestionId, Trashed>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Trashed {
+    question: DeletedQuestion,
+    answers: Vec<Answer>,
+    seq: Option<u64>,
+}
+
+// 日志中记录的每一次修改。重放时按 key 覆盖或删除，重复重放也不会出错
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", content = "data", rename_all = "snake_case")]
+enum Event {
+    AddQuestion(Question),
+    // 旧版本的日志中没有 revision
+    UpdateQuestion {
+        id: QuestionId,
+        question: Question,
+        #[serde(default)]
+        revision: Option<Revision>,
+    },
+    TrashQuestion {
+        id: QuestionId,
+        deleted_at: DateTime<Utc>,
+        deleted_by: String,
+    },
+    RestoreQuestion(QuestionId),
+    // 彻底删除，问题在回收站中时也一并删除
+    DeleteQuestion(QuestionId),
+    AddAnswer(Answer),
+    UpdateAnswer { id: AnswerId, answer: Answer },
+    DeleteAnswer(AnswerId),
+    SetTagAlias {
+        alias: String,
+        tag: String,
+        #[serde(default)]
+        change: TagChange,
+    },
+    DeleteTagAlias(String),
+    RenameTag {
+        from: String,
+        to: String,
+        #[serde(default)]
+        change: TagChange,
+    },
+    // 一次合并多个标签，在同一条日志中完成
+    MergeTags {
+        tags: Vec<String>,
+        into: String,
+        #[serde(default)]
+        change: TagChange,
+    },
+}
+
+// 标签改名时问题的修改时间和新的历史版本，在写日志前算好，重放时按版本号覆盖；旧版本的日志中没有
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TagChange {
+    updated_at: Option<DateTime<Utc>>,
+    revisions: Vec<Revision>,
+}
+
+impl State {
+    fn apply(&mut self, event: Event) {
+        match event {
+            Event::AddQuestion(question) => {
+                self.put_revision(Revision::initial(&question));
+                self.put_question(question.id.clone(), question);
+            }
+            Event::UpdateQuestion { id, question, revision } => {
+                if let Some(revision) = revision {
+                    self.put_revision(revision);
+                }
+                self.put_question(id, question);
+            }
+            Event::TrashQuestion { id, deleted_at, deleted_by } => {
+                if let Some((question, seq, answers)) = self.take_question(&id) {
+                    let question = DeletedQuestion { question, deleted_at, deleted_by };
+                    self.trash.insert(id, Trashed { question, answers, seq });
+                }
+            }
+            Event::RestoreQuestion(id) => {
+                if let Some(trashed) = self.trash.remove(&id) {
+                    // 回收站中问题的标签在改名时已经一起修改，原样放回即可
+                    if let Some(seq) = trashed.seq {
+                        self.created.insert(seq, id.clone());
+                        self.created_seq.insert(id.clone(), seq);
+                    }
+                    self.put_question(id, trashed.question.question);
+                    for answer in trashed.answers {
+                        self.put_answer(answer.id.clone(), answer);
+                    }
+                }
+            }
+            Event::DeleteQuestion(id) => {
+                self.take_question(&id);
+                self.trash.remove(&id);
+                self.revisions.remove(&id);
+            }
+            Event::AddAnswer(answer) => {
+                self.put_answer(answer.id.clone(), answer);
+            }
+            Event::UpdateAnswer { id, answer } => {
+                self.put_answer(id, answer);
+            }
+            Event::DeleteAnswer(id) => {
+                if let Some(answer) = self.answers.remove(&id) {
+                    self.unindex_answer(&answer.question_id, &id);
+                }
+            }
+            Event::SetTagAlias { alias, tag, change } => {
+                self.rename_tags(&BTreeMap::from([(alias.clone(), tag.clone())]), change);
+                self.aliases.insert(alias, tag);
+            }
+            Event::DeleteTagAlias(alias) => {
+                self.aliases.remove(&alias);
+            }
+            Event::RenameTag { from, to, change } => {
+                self.rename_tags(&BTreeMap::from([(from, to)]), change);
+            }
+            Event::MergeTags { tags, into, change } => {
+                self.rename_tags(&merge_map(tags, &into), change);
+            }
+        }
+    }
+
+    // 按 rename（旧标签 → 新标签）替换问题（包括回收站中的问题）中的标签，并让指向旧标签的别名改为指向新标签；
+    // 重复重放时找不到旧标签，不会有任何修改
+    fn rename_tags(&mut self, rename: &BTreeMap<String, String>, change: TagChange) {
+        for tag in self.aliases.values_mut() {
+            if let Some(to) = rename.get(tag) {
+                *tag = to.clone();
+            }
+        }
+        let renamed: Vec<Question> = self
+            .renamed_questions(rename, change.updated_at)
+            .into_iter()
+            .map(|(_, question)| question)
+            .collect();
+        for question in renamed {
+            match self.trash.get_mut(&question.id) {
+                Some(trashed) => trashed.question.question = question,
+                None => self.put_question(question.id.clone(), question),
+            }
+        }
+        for revision in change.revisions {
+            self.put_revision(revision);
+        }
+    }
+
+    // 被改名影响的问题：改名前和改名后（版本号加 1）的内容。回收站中的问题不在 tagged 索引中，逐个检查
+    fn renamed_questions(
+        &self,
+        rename: &BTreeMap<String, String>,
+        updated_at: Option<DateTime<Utc>>,
+    ) -> Vec<(&Question, Question)> {
+        let live = self
+            .tagged_with_any(rename.keys())
+            .into_iter()
+            .filter_map(|id| self.questions.get(&id));
+        let trashed = self
+            .trash
+            .values()
+            .map(|trashed| &trashed.question.question)
+            .filter(|question| question.tags.iter().flatten().any(|tag| rename.contains_key(tag)));
+        live.chain(trashed)
+            .map(|old| {
+                let mut question = old.clone();
+                question.tags = resolve_tags(question.tags, rename);
+                question.version += 1;
+                if let Some(updated_at) = updated_at {
+                    question.metadata.updated_at = updated_at;
+                }
+                (old, question)
+            })
+            .collect()
+    }
+
+    // 写日志之前算好改名产生的历史版本，每个被修改的问题一个
+    fn tag_change(&self, rename: &BTreeMap<String, String>, editor: &str) -> TagChange {
+        let now = Utc::now();
+        let revisions = self
+            .renamed_questions(rename, Some(now))
+            .iter()
+            .map(|(old, question)| {
+                let edit = Edit::rename_tags(editor, rename, &old.tags);
+                Revision::new(self.next_revision(&question.id), &question.id, question, &edit)
+            })
+            .collect();
+        TagChange {
+            updated_at: Some(now),
+            revisions,
+        }
+    }
+
+    // 至少使用了其中一个标签的问题
+    fn tagged_with_any<'a>(&self, tags: impl IntoIterator<Item = &'a String>) -> BTreeSet<QuestionId> {
+        tags.into_iter()
+            .filter_map(|tag| self.tagged.get(tag))
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    // 写入前规范化标签并替换别名
+    fn resolve_tags(&self, mut question: Question) -> Question {
+        question.tags = resolve_tags(question.tags, &self.aliases);
+        question
+    }
+
+    // 把问题和它的回答从所有索引中移出，返回问题、创建序号和回答
+    fn take_question(&mut self, id: &QuestionId) -> Option<(Question, Option<u64>, Vec<Answer>)> {
+        let question = self.questions.remove(id)?;
+        self.unindex_tags(id, &question);
+        self.search.remove(id);
+        let seq = self.created_seq.remove(id);
+        if let Some(seq) = seq {
+            self.created.remove(&seq);
+        }
+        let answers = self
+            .question_answers
+            .remove(id)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|answer_id| self.answers.remove(answer_id))
+            .collect();
+        Some((question, seq, answers))
+    }
+
+    // 回收站中的问题也占用 id
+    fn question_id_taken(&self, id: &QuestionId) -> bool {
+        self.questions.contains_key(id) || self.trash.contains_key(id)
+    }
+
+    // 回收站中问题的回答也占用 id
+    fn answer_id_taken(&self, id: &AnswerId) -> bool {
+        self.answers.contains_key(id)
+            || self.trash.values().any(|trashed| trashed.answers.iter().any(|answer| answer.id == *id))
+    }
+
+    fn put_revision(&mut self, revision: Revision) {
+        self.revisions
+            .entry(revision.question_id.clone())
+            .or_default()
+            .insert(revision.number, revision);
+    }
+
+    fn next_revision(&self, id: &QuestionId) -> u64 {
+        self.revisions
+            .get(id)
+            .and_then(|revisions| revisions.keys().next_back())
+            .map_or(1, |number| number + 1)
+    }
+
+    // 升级前保存的问题和回答没有时间、作者和版本号，也没有历史版本，启动时补上，下一次快照会写回磁盘
+    fn fill_missing_metadata(&mut self) {
+        let now = Utc::now();
+        for question in self.questions.values_mut() {
+            question.metadata.fill_missing(now);
+            question.version = question.version.max(1);
+            if !self.revisions.contains_key(&question.id) {
+                self.revisions
+                    .entry(question.id.clone())
+                    .or_default()
+                    .insert(1, Revision::initial(question));
+            }
+        }
+        for answer in self.answers.values_mut() {
+            answer.metadata.fill_missing(now);
+        }
+    }
+
+    // 升级前保存的数据可能还没有规范化，启动时统一处理一次
+    fn normalize_tags(&mut self) {
+        let changed: Vec<Question> = self
+            .questions
+            .values()
+            .filter_map(|question| {
+                let resolved = self.resolve_tags(question.clone());
+                (resolved.tags != question.tags).then_some(resolved)
+            })
+            .collect();
+        for question in changed {
+            self.put_question(question.id.clone(), question);
+        }
+    }
+
+    // 重放日志时同一个问题可能被添加多次，已有序号的保持不变；
+    // 快照中已经在回收站的问题沿用原来的序号，随后重放的 TrashQuestion 会把它再移回回收站
+    fn index_created(&mut self, id: &QuestionId) {
+        if self.created_seq.contains_key(id) {
+            return;
+        }
+        let seq = match self.trash.get(id).and_then(|trashed| trashed.seq) {
+            Some(seq) => seq,
+            None => {
+                let seq = self.next_seq;
+                self.next_seq += 1;
+                seq
+            }
+        };
+        self.created.insert(seq, id.clone());
+        self.created_seq.insert(id.clone(), seq);
+    }
+
+    fn put_question(&mut self, id: QuestionId, question: Question) {
+        self.index_created(&id);
+        if let Some(old) = self.questions.remove(&id) {
+            self.unindex_tags(&id, &old);
+        }
+        self.index_tags(&id, &question);
+        self.search.insert(&id, &question);
+        self.questions.insert(id, question);
+    }
+
+    fn index_tags(&mut self, id: &QuestionId, question: &Question) {
+        for tag in question.tags.iter().flatten() {
+            self.tagged.entry(tag.clone()).or_default().insert(id.clone());
+        }
+    }
+
+    fn unindex_tags(&mut self, id: &QuestionId, question: &Question) {
+        for tag in question.tags.iter().flatten() {
+            if let Some(ids) = self.tagged.get_mut(tag) {
+                ids.remove(id);
+                if ids.is_empty() {
+                    self.tagged.remove(tag);
+                }
+            }
+        }
+    }
+
+    // 匹配标签的问题 id：any 取并集，all 从最小的集合开始取交集
+    fn tagged_ids(&self, filter: &TagFilter) -> BTreeSet<QuestionId> {
+        let filter = filter.resolve(&self.aliases);
+        let sets = filter.tags.iter().map(|tag| self.tagged.get(tag));
+        match filter.mode {
+            TagMatch::Any => sets.flatten().flatten().cloned().collect(),
+            TagMatch::All => {
+                // 任意一个标签没有问题时交集为空
+                let Some(mut sets) = sets.collect::<Option<Vec<_>>>() else {
+                    return BTreeSet::new();
+                };
+                sets.sort_by_key(|ids| ids.len());
+                match sets.split_first() {
+                    Some((first, rest)) => first
+                        .iter()
+                        .filter(|id| rest.iter().all(|ids| ids.contains(*id)))
+                        .cloned()
+                        .collect(),
+                    None => BTreeSet::new(),
+                }
+            }
+        }
+    }
+
+    // 匹配标签的问题，按 sort 排好序并带上各自的游标
+    fn tagged_questions(&self, sort: SortOrder, filter: &TagFilter) -> Vec<(Cursor, &Question)> {
+        let mut items: Vec<(Cursor, &Question)> = self
+            .tagged_ids(filter)
+            .into_iter()
+            .filter_map(|id| {
+                let question = self.questions.get(&id)?;
+                let cursor = match sort {
+                    SortOrder::Created => Cursor::Created(*self.created_seq.get(&id)?),
+                    SortOrder::Id => Cursor::Id(id.0),
+                };
+                Some((cursor, question))
+            })
+            .collect();
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+        items
+    }
+
+    fn check_question_exists(&self, id: &QuestionId) -> Result<(), Error> {
+        match self.questions.contains_key(id) {
+            true => Ok(()),
+            false => Err(Error::UnknownQuestion(id.0.clone())),
+        }
+    }
+
+    fn put_answer(&mut self, id: AnswerId, answer: Answer) {
+        if let Some(old) = self.answers.get(&id) {
+            let question_id = old.question_id.clone();
+            self.unindex_answer(&question_id, &id);
+        }
+        self.question_answers
+            .entry(answer.question_id.clone())
+            .or_default()
+            .insert(id.clone());
+        self.answers.insert(id, answer);
+    }
+
+    fn unindex_answer(&mut self, question_id: &QuestionId, id: &AnswerId) {
+        if let Some(ids) = self.question_answers.get_mut(question_id) {
+            ids.remove(id);
+            if ids.is_empty() {
+                self.question_answers.remove(question_id);
+            }
+        }
+    }
+
+    fn reindex(&mut self) {
+        // 快照中的创建顺序可能落后于 questions.json，缺少的问题按 id 顺序补在最后
+        let questions = &self.questions;
+        self.created.retain(|_, id| questions.contains_key(id));
+        self.created_seq = self.created.iter().map(|(seq, id)| (id.clone(), *seq)).collect();
+        let trashed = self.trash.values().filter_map(|trashed| trashed.seq);
+        if let Some(last) = self.created.keys().copied().chain(trashed).max() {
+            self.next_seq = self.next_seq.max(last + 1);
+        }
+        let ids: Vec<QuestionId> = self.questions.keys().cloned().collect();
+        for id in &ids {
+            self.index_created(id);
+        }
+
+        self.search = SearchIndex::build(&self.questions);
+        self.tagged.clear();
+        for (id, question) in &self.questions {
+            for tag in question.tags.iter().flatten() {
+                self.tagged.entry(tag.clone()).or_default().insert(id.clone());
+            }
+        }
+
+        self.question_answers.clear();
+        for (id, answer) in &self.answers {
+            self.question_answers
+                .entry(answer.question_id.clone())
+                .or_default()
+                .insert(id.clone());
+        }
+    }
+}
+
+impl Store {
+    pub fn new() -> Self {
+        Store {
+            state: Arc::new(RwLock::new(State::default())),
+            journal: None,
+        }
+    }
+
+    // 从数据目录恢复：先读快照，再重放快照之后的日志，最后重新生成快照
+    pub async fn open(dir: &Path) -> Result<Self, Error> {
+        let journal = Journal::open(dir)?;
+        let mut state = match journal.load_snapshot::<BTreeMap<QuestionId, Question>, State>()? {
+            Some((questions, mut state)) => {
+                state.questions = questions;
+                state
+            }
+            None => State::default(),
+        };
+
+        let events = journal.replay::<Event>()?;
+        log::info!("Replaying {} journal entries from {}", events.len(), dir.display());
+        state.reindex();
+        for event in events {
+            state.apply(event);
+        }
+        state.normalize_tags();
+        state.fill_missing_metadata();
+
+        let store = Store {
+            state: Arc::new(RwLock::new(state)),
+            journal: Some(Arc::new(journal)),
+        };
+        store.compact().await?;
+        Ok(store)
+    }
+
+    pub async fn compact(&self) -> Result<(), Error> {
+        if let Some(journal) = &self.journal {
+            // 持有读锁直到快照写完，期间不会有新的修改写入日志
+            let state = self.state.read().await;
+            let questions = encode_snapshot(&state.questions)?;
+            let snapshot = encode_snapshot(&*state)?;
+            let journal = journal.clone();
+            blocking(move || journal.compact(&questions, &snapshot)).await?;
+        }
+        Ok(())
+    }
+
+    pub fn spawn_compaction(&self, every: Duration) {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if let Err(e) = store.compact().await {
+                    log::error!("Snapshot failed: {}", e);
+                }
+            }
+        });
+    }
+
+    // 在持有写锁时调用：先写日志，成功后再修改内存；写完日志之前不释放写锁，日志的顺序与修改的顺序一致
+    async fn commit(&self, state: &mut State, event: Event) -> Result<(), Error> {
+        if let Some(journal) = &self.journal {
+            let line = encode_event(&event)?;
+            let journal = journal.clone();
+            blocking(move || journal.append(&line)).await?;
+        }
+        state.apply(event);
+        Ok(())
+    }
+}
+
+// 文件 I/O 放到阻塞线程池中执行，不占用 tokio 的工作线程
+async fn blocking<F>(f: F) -> Result<(), Error>
+where
+    F: FnOnce() -> Result<(), Error> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| Error::JournalError(std::io::Error::other(e)))?
+}
+
+#[async_trait]
+impl QuestionRepository for Store {
+    async fn get_questions(
+        &self,
+        sort: SortOrder,
+        tags: Option<&TagFilter>,
+        pagination: Option<Pagination>,
+    ) -> Result<Vec<Question>, Error> {
+        let state = self.state.read().await;
+        if let Some(filter) = tags {
+            let questions = state.tagged_questions(sort, filter);
+            return Ok(paginate(questions.into_iter().map(|(_, q)| q), pagination));
+        }
+        let questions = match sort {
+            SortOrder::Created => paginate(
+                state.created.values().filter_map(|id| state.questions.get(id)),
+                pagination,
+            ),
+            SortOrder::Id => paginate(state.questions.values(), pagination),
+        };
+        Ok(questions)
+    }
+
+    async fn count_questions(&self, tags: Option<&TagFilter>) -> Result<usize, Error> {
+        let state = self.state.read().await;
+        match tags {
+            Some(filter) => Ok(state.tagged_ids(filter).len()),
+            None => Ok(state.questions.len()),
+        }
+    }
+
+    async fn get_questions_after(
+        &self,
+        sort: SortOrder,
+        tags: Option<&TagFilter>,
+        pagination: CursorPagination,
+    ) -> Result<(Vec<Question>, Option<Cursor>), Error> {
+        let state = self.state.read().await;
+        if let Some(filter) = tags {
+            let after = pagination.after.as_ref();
+            if !after.is_none_or(|after| after.matches(sort)) {
+                return Err(Error::InvalidCursor);
+            }
+            let items = state
+                .tagged_questions(sort, filter)
+                .into_iter()
+                .filter(|(cursor, _)| after.is_none_or(|after| cursor > after));
+            return Ok(keyset_page(items, pagination.limit));
+        }
+        let page = match (sort, pagination.after) {
+            (SortOrder::Created, after @ (None | Some(Cursor::Created(_)))) => {
+                let start = match after {
+                    Some(Cursor::Created(seq)) => Excluded(seq),
+                    _ => Unbounded,
+                };
+                let items = state
+                    .created
+                    .range((start, Unbounded))
+                    .filter_map(|(seq, id)| state.questions.get(id).map(|q| (Cursor::Created(*seq), q)));
+                keyset_page(items, pagination.limit)
+            }
+            (SortOrder::Id, after @ (None | Some(Cursor::Id(_)))) => {
+                let start = match after {
+                    Some(Cursor::Id(id)) => Excluded(QuestionId(id)),
+                    _ => Unbounded,
+                };
+                let items = state
+                    .questions
+                    .range((start, Unbounded))
+                    .map(|(id, q)| (Cursor::Id(id.0.clone()), q));
+                keyset_page(items, pagination.limit)
+            }
+            _ => return Err(Error::InvalidCursor),
+        };
+        Ok(page)
+    }
+
+    async fn get_question(&self, id: &QuestionId) -> Result<Question, Error> {
+        match self.state.read().await.questions.get(id) {
+            Some(question) => Ok(question.clone()),
+            None => Err(Error::QuestionNotFound),
+        }
+    }
+
+    async fn add_question(&self, mut question: Question) -> Result<Question, Error> {
+        let mut state = self.state.write().await;
+        if state.question_id_taken(&question.id) {
+            return Err(Error::QuestionAlreadyExists(question.id.0));
+        }
+        question.version = 1;
+        let question = state.resolve_tags(question);
+        self.commit(&mut state, Event::AddQuestion(question.clone())).await?;
+        Ok(question)
+    }
+
+    async fn update_question(
+        &self,
+        id: &QuestionId,
+        mut question: Question,
+        edit: &Edit,
+        if_match: &IfMatch,
+    ) -> Result<Question, Error> {
+        let mut state = self.state.write().await;
+        let Some(existing) = state.questions.get(id) else {
+            return Err(Error::QuestionNotFound);
+        };
+        if_match.check(existing.version)?;
+        // 创建时间和作者不允许修改
+        question.metadata.created_at = existing.metadata.created_at;
+        question.metadata.author = existing.metadata.author.clone();
+        question.version = existing.version + 1;
+        let question = state.resolve_tags(question);
+        let revision = Revision::new(state.next_revision(id), id, &question, edit);
+        self.commit(&mut state, Event::UpdateQuestion {
+            id: id.clone(),
+            question: question.clone(),
+            revision: Some(revision),
+        }).await?;
+        Ok(question)
+    }
+
+    async fn get_revisions(&self, id: &QuestionId) -> Result<Vec<Revision>, Error> {
+        let state = self.state.read().await;
+        if !state.questions.contains_key(id) {
+            return Err(Error::QuestionNotFound);
+        }
+        Ok(state.revisions.get(id).into_iter().flat_map(|r| r.values().cloned()).collect())
+    }
+
+    async fn get_revision(&self, id: &QuestionId, number: u64) -> Result<Revision, Error> {
+        let state = self.state.read().await;
+        if !state.questions.contains_key(id) {
+            return Err(Error::QuestionNotFound);
+        }
+        state
+            .revisions
+            .get(id)
+            .and_then(|revisions| revisions.get(&number))
+            .cloned()
+            .ok_or(Error::RevisionNotFound(number))
+    }
+
+    async fn delete_question(&self, id: &QuestionId, deleted_by: &str, if_match: &IfMatch) -> Result<(), Error> {
+        let mut state = self.state.write().await;
+        let Some(existing) = state.questions.get(id) else {
+            return Err(Error::QuestionNotFound);
+        };
+        if_match.check(existing.version)?;
+        self.commit(&mut state, Event::TrashQuestion {
+            id: id.clone(),
+            deleted_at: Utc::now(),
+            deleted_by: deleted_by.to_string(),
+        }).await
+    }
+
+    async fn restore_question(&self, id: &QuestionId) -> Result<Question, Error> {
+        let mut state = self.state.write().await;
+        if !state.trash.contains_key(id) {
+            return Err(Error::QuestionNotFound);
+        }
+        self.commit(&mut state, Event::RestoreQuestion(id.clone())).await?;
+        state
+            .questions
+            .get(id)
+            .cloned()
+            .ok_or_else(|| Error::InternalError(format!("restored question '{}' is missing", id.0)))
+    }
+
+    async fn get_deleted_questions(&self) -> Result<Vec<DeletedQuestion>, Error> {
+        let state = self.state.read().await;
+        let mut questions: Vec<DeletedQuestion> =
+            state.trash.values().map(|trashed| trashed.question.clone()).collect();
+        questions.sort_by_key(|question| Reverse(question.deleted_at));
+        Ok(questions)
+    }
+
+    async fn purge_deleted(&self, before: DateTime<Utc>) -> Result<usize, Error> {
+        let mut state = self.state.write().await;
+        let expired: Vec<QuestionId> = state
+            .trash
+            .iter()
+            .filter(|(_, trashed)| trashed.question.deleted_at < before)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &expired {
+            self.commit(&mut state, Event::DeleteQuestion(id.clone())).await?;
+        }
+        Ok(expired.len())
+    }
+
+    async fn search_questions(
+        &self,
+        query: &str,
+        pagination: Pagination,
+    ) -> Result<(Vec<(Question, f64)>, usize), Error> {
+        let state = self.state.read().await;
+        let results = state.search.search(query);
+        let page = results
+            .iter()
+            .skip(pagination.start)
+            .take(pagination.limit())
+            .filter_map(|(id, score)| state.questions.get(id).map(|q| (q.clone(), *score)))
+            .collect();
+        Ok((page, results.len()))
+    }
+}
+
+#[async_trait]
+impl AnswerRepository for Store {
+    async fn get_answers_for_question(
+        &self,
+        question_id: &QuestionId,
+        pagination: Option<Pagination>,
+    ) -> Result<Vec<Answer>, Error> {
+        let state = self.state.read().await;
+        let answers = state
+            .question_answers
+            .get(question_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| state.answers.get(id));
+        Ok(paginate(answers, pagination))
+    }
+
+    async fn count_answers_for_question(&self, question_id: &QuestionId) -> Result<usize, Error> {
+        let state = self.state.read().await;
+        Ok(state.question_answers.get(question_id).map_or(0, |ids| ids.len()))
+    }
+
+    async fn get_answers_for_question_after(
+        &self,
+        question_id: &QuestionId,
+        pagination: CursorPagination,
+    ) -> Result<(Vec<Answer>, Option<Cursor>), Error> {
+        let start = match pagination.after {
+            Some(Cursor::Id(id)) => Excluded(AnswerId(id)),
+            None => Unbounded,
+            Some(_) => return Err(Error::InvalidCursor),
+        };
+        let state = self.state.read().await;
+        let answers = state
+            .question_answers
+            .get(question_id)
+            .into_iter()
+            .flat_map(|ids| ids.range((start.clone(), Unbounded)))
+            .filter_map(|id| state.answers.get(id).map(|a| (Cursor::Id(id.0.clone()), a)));
+        Ok(keyset_page(answers, pagination.limit))
+    }
+
+    async fn get_answer(&self, id: &AnswerId) -> Result<Answer, Error> {
+        match self.state.read().await.answers.get(id) {
+            Some(answer) => Ok(answer.clone()),
+            None => Err(Error::AnswerNotFound),
+        }
+    }
+
+    async fn add_answer(&self, answer: Answer) -> Result<Answer, Error> {
+        let mut state = self.state.write().await;
+        state.check_question_exists(&answer.question_id)?;
+        if state.answer_id_taken(&answer.id) {
+            return Err(Error::AnswerAlreadyExists(answer.id.0));
+        }
+        self.commit(&mut state, Event::AddAnswer(answer.clone())).await?;
+        Ok(answer)
+    }
+
+    async fn update_answer(&self, id: &AnswerId, mut answer: Answer) -> Result<Answer, Error> {
+        let mut state = self.state.write().await;
+        let Some(existing) = state.answers.get(id) else {
+            return Err(Error::AnswerNotFound);
+        };
+        answer.metadata.created_at = existing.metadata.created_at;
+        answer.metadata.author = existing.metadata.author.clone();
+        state.check_question_exists(&answer.question_id)?;
+        self.commit(&mut state, Event::UpdateAnswer { id: id.clone(), answer: answer.clone() }).await?;
+        Ok(answer)
+    }
+
+    async fn delete_answer(&self, id: &AnswerId) -> Result<(), Error> {
+        let mut state = self.state.write().await;
+        if !state.answers.contains_key(id) {
+            return Err(Error::AnswerNotFound);
+        }
+        self.commit(&mut state, Event::DeleteAnswer(id.clone())).await
+    }
+}
+
+#[async_trait]
+impl TagRepository for Store {
+    async fn get_tags(&self) -> Result<Vec<TagCount>, Error> {
+        let state = self.state.read().await;
+        let mut tags: Vec<TagCount> = state
+            .tagged
+            .iter()
+            .map(|(name, ids)| TagCount { name: name.clone(), count: ids.len() })
+            .collect();
+        tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+        Ok(tags)
+    }
+
+    async fn get_tag_aliases(&self) -> Result<Vec<TagAlias>, Error> {
+        let state = self.state.read().await;
+        Ok(state
+            .aliases
+            .iter()
+            .map(|(alias, tag)| TagAlias { alias: alias.clone(), tag: tag.clone() })
+            .collect())
+    }
+
+    async fn set_tag_alias(&self, alias: &str, tag: &str, editor: &str) -> Result<TagAlias, Error> {
+        let alias = required_tag(alias)?;
+        let tag = required_tag(tag)?;
+        let mut state = self.state.write().await;
+        let tag = state.aliases.get(&tag).cloned().unwrap_or(tag);
+        if alias == tag {
+            return Err(Error::InvalidTagAlias(alias));
+        }
+        let change = state.tag_change(&BTreeMap::from([(alias.clone(), tag.clone())]), editor);
+        self.commit(&mut state, Event::SetTagAlias { alias: alias.clone(), tag: tag.clone(), change }).await?;
+        Ok(TagAlias { alias, tag })
+    }
+
+    async fn delete_tag_alias(&self, alias: &str) -> Result<(), Error> {
+        let alias = required_tag(alias)?;
+        let mut state = self.state.write().await;
+        if !state.aliases.contains_key(&alias) {
+            return Err(Error::TagAliasNotFound(alias));
+        }
+        self.commit(&mut state, Event::DeleteTagAlias(alias)).await
+    }
+
+    async fn rename_tag(&self, from: &str, to: &str, editor: &str) -> Result<usize, Error> {
+        let from = required_tag(from)?;
+        let to = required_tag(to)?;
+        let mut state = self.state.write().await;
+        let to = state.aliases.get(&to).cloned().unwrap_or(to);
+        let rename = BTreeMap::from([(from.clone(), to.clone())]);
+        // 只有回收站中的问题使用这个标签时也可以改名
+        let count = state.renamed_questions(&rename, None).len();
+        if count == 0 {
+            return Err(Error::TagNotFound(from));
+        }
+        if from == to {
+            return Ok(0);
+        }
+        let change = state.tag_change(&rename, editor);
+        self.commit(&mut state, Event::RenameTag { from, to, change }).await?;
+        Ok(count)
+    }
+
+    async fn merge_tags(&self, tags: &[String], into: &str, editor: &str) -> Result<usize, Error> {
+        let tags = tags.iter().map(|tag| required_tag(tag)).collect::<Result<Vec<String>, Error>>()?;
+        let into = required_tag(into)?;
+        let mut state = self.state.write().await;
+        let into = state.aliases.get(&into).cloned().unwrap_or(into);
+        let rename = merge_map(tags, &into);
+        if rename.is_empty() {
+            return Ok(0);
+        }
+        let change = state.tag_change(&rename, editor);
+        let count = change.revisions.len();
+        let tags = rename.into_keys().collect();
+        self.commit(&mut state, Event::MergeTags { tags, into, change }).await?;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::types::metadata::Metadata;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("ch06-journal-{}", uuid::Uuid::new_v4()))
+    }
+
+    fn question(id: &str, tags: &[&str]) -> Question {
+        Question {
+            id: QuestionId(id.to_string()),
+            title: format!("Question {}", id),
+            content: "How do warp filters compose?".to_string(),
+            tags: Some(tags.iter().map(|tag| tag.to_string()).collect()),
+            metadata: Metadata::new("alice".to_string(), Utc::now()),
+            version: 1,
+        }
+    }
+
+    // 覆盖日志中的每一种修改
+    async fn write_history(store: &Store) {
+        store.add_question(question("1", &["rust", "warp"])).await.unwrap();
+        store.add_question(question("2", &["rust"])).await.unwrap();
+        store.add_question(question("3", &["web"])).await.unwrap();
+        let answer = Answer {
+            id: AnswerId("a1".to_string()),
+            content: "Use and()".to_string(),
+            question_id: QuestionId("1".to_string()),
+            metadata: Metadata::new("bob".to_string(), Utc::now()),
+        };
+        store.add_answer(answer).await.unwrap();
+        let mut update = question("1", &["rust", "warp"]);
+        update.title = "Composing warp filters".to_string();
+        let edit = Edit { editor: "bob".to_string(), summary: Some("title".to_string()) };
+        store.update_question(&QuestionId("1".to_string()), update, &edit, &IfMatch(None)).await.unwrap();
+        store.rename_tag("warp", "warp-rs", "carol").await.unwrap();
+        store.set_tag_alias("rust-lang", "rust", "carol").await.unwrap();
+        store.delete_question(&QuestionId("3".to_string()), "alice", &IfMatch(None)).await.unwrap();
+        store.rename_tag("web", "web-dev", "carol").await.unwrap();
+    }
+
+    // 比较重启前后可以观察到的全部状态
+    async fn dump(store: &Store) -> String {
+        let state = store.state.read().await;
+        let trash: Vec<_> = state.trash.iter().map(|(id, t)| (id, &t.question, &t.answers, t.seq)).collect();
+        let mut answers: Vec<_> = state.answers.values().collect();
+        answers.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut revisions: Vec<_> = state.revisions.iter().collect();
+        revisions.sort_by(|a, b| a.0.cmp(b.0));
+        serde_json::to_string(&(
+            &state.questions,
+            &state.created,
+            answers,
+            &state.aliases,
+            revisions,
+            trash,
+        ))
+        .unwrap()
+    }
+
+    // 快照写完、日志还没清空时崩溃，重启后会在新快照上再重放一遍同样的日志
+    async fn replay_after_partial_compaction(keep_old_state: bool) {
+        let dir = temp_dir();
+        let store = Store::open(&dir).await.unwrap();
+        let old_state = fs::read(dir.join("state.json")).unwrap();
+        write_history(&store).await;
+        let expected = dump(&store).await;
+
+        let journal = fs::read(dir.join("journal.log")).unwrap();
+        store.compact().await.unwrap();
+        fs::write(dir.join("journal.log"), journal).unwrap();
+        if keep_old_state {
+            // questions.json 已经替换，state.json 还是旧的
+            fs::write(dir.join("state.json"), old_state).unwrap();
+        }
+        drop(store);
+
+        let store = Store::open(&dir).await.unwrap();
+        assert_eq!(dump(&store).await, expected);
+        let renamed = store.get_question(&QuestionId("1".to_string())).await.unwrap();
+        assert_eq!(renamed.tags, Some(vec!["rust".to_string(), "warp-rs".to_string()]));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn replay_is_idempotent_after_compaction() {
+        replay_after_partial_compaction(false).await;
+    }
+
+    #[tokio::test]
+    async fn replay_with_stale_state_snapshot() {
+        replay_after_partial_compaction(true).await;
+    }
+
+    #[tokio::test]
+    async fn answer_id_collision_does_not_overwrite() {
+        let store = Store::new();
+        store.add_question(question("1", &[])).await.unwrap();
+        let answer = |content: &str| Answer {
+            id: AnswerId("a1".to_string()),
+            content: content.to_string(),
+            question_id: QuestionId("1".to_string()),
+            metadata: Metadata::new("bob".to_string(), Utc::now()),
+        };
+        store.add_answer(answer("first")).await.unwrap();
+        let result = store.add_answer(answer("second")).await;
+        assert!(matches!(result, Err(Error::AnswerAlreadyExists(id)) if id == "a1"));
+
+        // 问题在回收站中时，它的回答仍然占用 id
+        store.delete_question(&QuestionId("1".to_string()), "alice", &IfMatch(None)).await.unwrap();
+        store.add_question(question("2", &[])).await.unwrap();
+        let mut moved = answer("third");
+        moved.question_id = QuestionId("2".to_string());
+        assert!(matches!(store.add_answer(moved).await, Err(Error::AnswerAlreadyExists(_))));
+        store.restore_question(&QuestionId("1".to_string())).await.unwrap();
+        let kept = store.get_answer(&AnswerId("a1".to_string())).await.unwrap();
+        assert_eq!(kept.content, "first");
+    }
+
+    #[tokio::test]
+    async fn merge_tags_is_a_single_change() {
+        let dir = temp_dir();
+        let store = Store::open(&dir).await.unwrap();
+        store.add_question(question("1", &["rust-lang", "rustlang", "web"])).await.unwrap();
+        store.add_question(question("2", &["rustlang"])).await.unwrap();
+        store.set_tag_alias("rs", "rustlang", "carol").await.unwrap();
+        let before = fs::read_to_string(dir.join("journal.log")).unwrap().lines().count();
+
+        let tags = ["rust-lang".to_string(), "RustLang".to_string(), "unused".to_string(), "rust".to_string()];
+        assert_eq!(store.merge_tags(&tags, "rust", "carol").await.unwrap(), 2);
+
+        let journal = fs::read_to_string(dir.join("journal.log")).unwrap();
+        assert_eq!(journal.lines().count(), before + 1);
+        let first = store.get_question(&QuestionId("1".to_string())).await.unwrap();
+        assert_eq!(first.version, 2);
+        assert_eq!(first.tags, Some(vec!["rust".to_string(), "web".to_string()]));
+        // 最新的历史版本与问题当前的内容一致
+        let revisions = store.get_revisions(&first.id).await.unwrap();
+        let latest = revisions.last().unwrap();
+        assert_eq!((latest.number, &latest.tags, latest.editor.as_str()), (2, &first.tags, "carol"));
+        assert_eq!(latest.summary.as_deref(), Some("merged tags 'rust-lang', 'rustlang' into 'rust'"));
+        assert_eq!(latest.created_at, first.metadata.updated_at);
+        let second = store.get_revisions(&QuestionId("2".to_string())).await.unwrap();
+        assert_eq!(second.last().unwrap().summary.as_deref(), Some("renamed tag 'rustlang' to 'rust'"));
+        let aliases = store.get_tag_aliases().await.unwrap();
+        assert_eq!(aliases.iter().map(|a| (a.alias.as_str(), a.tag.as_str())).collect::<Vec<_>>(), [("rs", "rust")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rename_rewrites_trashed_questions() {
+        let dir = temp_dir();
+        let store = Store::open(&dir).await.unwrap();
+        store.add_question(question("1", &["rust", "warp"])).await.unwrap();
+        store.add_question(question("2", &["warp"])).await.unwrap();
+        let id = QuestionId("1".to_string());
+        store.delete_question(&id, "alice", &IfMatch(None)).await.unwrap();
+
+        assert_eq!(store.rename_tag("warp", "warp-rs", "carol").await.unwrap(), 2);
+        store.delete_question(&QuestionId("2".to_string()), "alice", &IfMatch(None)).await.unwrap();
+        // 只剩回收站中的问题使用这个标签
+        assert_eq!(store.rename_tag("rust", "rust-lang", "carol").await.unwrap(), 1);
+        assert!(store.get_tags().await.unwrap().is_empty());
+        drop(store);
+
+        let store = Store::open(&dir).await.unwrap();
+        let restored = store.restore_question(&id).await.unwrap();
+        assert_eq!(restored.tags, Some(vec!["rust-lang".to_string(), "warp-rs".to_string()]));
+        assert_eq!(restored.version, 3);
+        let revisions = store.get_revisions(&id).await.unwrap();
+        assert_eq!(revisions.last().unwrap().summary.as_deref(), Some("renamed tag 'rust' to 'rust-lang'"));
+        let tags: Vec<_> = store.get_tags().await.unwrap().into_iter().map(|tag| tag.name).collect();
+        assert_eq!(tags, ["rust-lang", "warp-rs"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn torn_last_line_is_discarded() {
+        let dir = temp_dir();
+        let store = Store::open(&dir).await.unwrap();
+        write_history(&store).await;
+        let expected = dump(&store).await;
+        drop(store);
+
+        let mut journal = fs::read_to_string(dir.join("journal.log")).unwrap();
+        journal.push_str(r#"{"op":"add_question","data":{"id":"4","ti"#);
+        fs::write(dir.join("journal.log"), journal).unwrap();
+
+        let store = Store::open(&dir).await.unwrap();
+        assert_eq!(dump(&store).await, expected);
+        assert!(matches!(store.get_question(&QuestionId("4".to_string())).await, Err(Error::QuestionNotFound)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,359 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use handle_errors::Error;
+
+use crate::types::etag::IfMatch;
+use crate::types::pagination::{Cursor, CursorPagination, Pagination, SortOrder};
+use crate::types::revision::{Edit, Revision};
+use crate::types::tag::{TagAlias, TagCount, TagFilter};
+use crate::types::question::DeletedQuestion;
+use crate::{Answer, AnswerId, Question, QuestionId};
+
+mod journal;
+mod memory;
+mod seed;
+mod sqlite;
+
+pub use memory::Store;
+pub use seed::{load_seed, seed_store};
+pub use sqlite::SqliteStore;
+
+// 路由处理函数只依赖下面的 trait，具体的存储后端可以随意替换
+#[async_trait]
+pub trait QuestionRepository: Send + Sync {
+    // tags 为 None 时返回所有问题，否则只返回匹配标签的问题
+    async fn get_questions(
+        &self,
+        sort: SortOrder,
+        tags: Option<&TagFilter>,
+        pagination: Option<Pagination>,
+    ) -> Result<Vec<Question>, Error>;
+    async fn count_questions(&self, tags: Option<&TagFilter>) -> Result<usize, Error>;
+    // 返回 after 之后的 limit 条数据，以及下一页的游标
+    async fn get_questions_after(
+        &self,
+        sort: SortOrder,
+        tags: Option<&TagFilter>,
+        pagination: CursorPagination,
+    ) -> Result<(Vec<Question>, Option<Cursor>), Error>;
+    async fn get_question(&self, id: &QuestionId) -> Result<Question, Error>;
+    // id 已存在时返回 Error::QuestionAlreadyExists，不会覆盖
+    async fn add_question(&self, question: Question) -> Result<Question, Error>;
+    // 每次修改都会记录一个新的版本，版本号加 1；if_match 不匹配当前版本号时返回 Error::PreconditionFailed
+    async fn update_question(
+        &self,
+        id: &QuestionId,
+        question: Question,
+        edit: &Edit,
+        if_match: &IfMatch,
+    ) -> Result<Question, Error>;
+    // 按版本号从小到大排列
+    async fn get_revisions(&self, id: &QuestionId) -> Result<Vec<Revision>, Error>;
+    async fn get_revision(&self, id: &QuestionId, number: u64) -> Result<Revision, Error>;
+    // 把问题连同回答移到回收站，之后的查询都看不到它，直到恢复或被清理
+    async fn delete_question(&self, id: &QuestionId, deleted_by: &str, if_match: &IfMatch) -> Result<(), Error>;
+    // 从回收站恢复，不在回收站中时返回 Error::QuestionNotFound
+    async fn restore_question(&self, id: &QuestionId) -> Result<Question, Error>;
+    // 回收站中的问题，最近删除的在前
+    async fn get_deleted_questions(&self) -> Result<Vec<DeletedQuestion>, Error>;
+    // 彻底删除 before 之前移到回收站的问题及其回答，返回删除的数量
+    async fn purge_deleted(&self, before: DateTime<Utc>) -> Result<usize, Error>;
+    // 全文搜索，返回按相关度排序的一页结果（问题和得分）以及匹配的总数
+    async fn search_questions(
+        &self,
+        query: &str,
+        pagination: Pagination,
+    ) -> Result<(Vec<(Question, f64)>, usize), Error>;
+}
+
+#[async_trait]
+pub trait AnswerRepository: Send + Sync {
+    async fn get_answers_for_question(
+        &self,
+        question_id: &QuestionId,
+        pagination: Option<Pagination>,
+    ) -> Result<Vec<Answer>, Error>;
+    async fn count_answers_for_question(&self, question_id: &QuestionId) -> Result<usize, Error>;
+    // 回答按 id 排序，游标只能是 Cursor::Id
+    async fn get_answers_for_question_after(
+        &self,
+        question_id: &QuestionId,
+        pagination: CursorPagination,
+    ) -> Result<(Vec<Answer>, Option<Cursor>), Error>;
+    async fn get_answer(&self, id: &AnswerId) -> Result<Answer, Error>;
+    // 回答引用的问题不存在时返回 Error::UnknownQuestion，id 已存在时返回 Error::AnswerAlreadyExists，不会覆盖
+    async fn add_answer(&self, answer: Answer) -> Result<Answer, Error>;
+    async fn update_answer(&self, id: &AnswerId, answer: Answer) -> Result<Answer, Error>;
+    async fn delete_answer(&self, id: &AnswerId) -> Result<(), Error>;
+}
+
+// 标签在写入问题时会被规范化（去掉多余空白、转成小写）并替换别名
+// 改名、合并和添加别名修改了问题时，问题的版本号加 1，并以 editor 的名义记录一个新的历史版本
+#[async_trait]
+pub trait TagRepository: Send + Sync {
+    // 按使用次数从多到少排列
+    async fn get_tags(&self) -> Result<Vec<TagCount>, Error>;
+    async fn get_tag_aliases(&self) -> Result<Vec<TagAlias>, Error>;
+    // 已有问题（包括回收站中的问题）中的 alias 也会被替换成 tag；tag 本身是别名时使用它指向的标签
+    async fn set_tag_alias(&self, alias: &str, tag: &str, editor: &str) -> Result<TagAlias, Error>;
+    async fn delete_tag_alias(&self, alias: &str) -> Result<(), Error>;
+    // 把所有问题（包括回收站中的问题）中的 from 改成 to，to 已经被使用时两者合并；返回修改的问题数
+    async fn rename_tag(&self, from: &str, to: &str, editor: &str) -> Result<usize, Error>;
+    // 把 tags 全部改名为 into，在一个事务（一条日志）中完成；没有被使用的标签直接跳过，返回修改的问题数
+    async fn merge_tags(&self, tags: &[String], into: &str, editor: &str) -> Result<usize, Error>;
+}
+
+pub trait Repository: QuestionRepository + AnswerRepository + TagRepository {}
+
+impl<T: QuestionRepository + AnswerRepository + TagRepository> Repository for T {}
+
+// 注入到 warp filter 中的共享存储
+pub type SharedStore = Arc<dyn Repository>;
+
+// 定期清理回收站中超过保留时间的问题，启动时先清理一次
+pub fn spawn_purge(store: SharedStore, retention: Duration) {
+    let every = retention.min(Duration::from_secs(3600)).max(Duration::from_secs(1));
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        loop {
+            interval.tick().await;
+            let before = Utc::now() - retention;
+            match store.purge_deleted(before).await {
+                Ok(0) => {}
+                Ok(count) => log::info!("Purged {} deleted questions", count),
+                Err(e) => log::error!("Purging deleted questions failed: {}", e),
+            }
+        }
+    });
+}
+
+// 从 (游标, 数据) 的有序迭代器中取 limit 条，多取一条用来判断是否还有下一页
+pub(crate) fn keyset_page<'a, T, I>(mut items: I, limit: usize) -> (Vec<T>, Option<Cursor>)
+where
+    T: Clone + 'a,
+    I: Iterator<Item = (Cursor, &'a T)>,
+{
+    let mut page = Vec::with_capacity(limit);
+    let mut last = None;
+    for (cursor, item) in items.by_ref().take(limit) {
+        page.push(item.clone());
+        last = Some(cursor);
+    }
+    match items.next() {
+        Some(_) => (page, last),
+        None => (page, None),
+    }
+}
+
+// 从已经排好序的迭代器中按 start/end 截取一页，只克隆这一页的数据，越界时返回空
+pub(crate) fn paginate<'a, T, I>(items: I, pagination: Option<Pagination>) -> Vec<T>
+where
+    T: Clone + 'a,
+    I: Iterator<Item = &'a T>,
+{
+    match pagination {
+        None => items.cloned().collect(),
+        Some(pagination) => items
+            .skip(pagination.start)
+            .take(pagination.end.saturating_sub(pagination.start))
+            .cloned()
+            .collect(),
+    }
+}
+
+// 两种存储后端需要表现一致，下面的测试对两者运行同样的步骤
+#[cfg(test)]
+mod tests {
+    use crate::types::metadata::Metadata;
+
+    use super::*;
+
+    // 每个测试一个独立的内存数据库，连接池中的连接通过共享缓存访问同一个数据库
+    pub(super) async fn sqlite_store() -> SqliteStore {
+        let url = format!("sqlite:file:ch06-{}?mode=memory&cache=shared", uuid::Uuid::new_v4());
+        SqliteStore::new(&url).await.unwrap()
+    }
+
+    async fn stores() -> Vec<(&'static str, SharedStore)> {
+        vec![("memory", Arc::new(Store::new())), ("sqlite", Arc::new(sqlite_store().await))]
+    }
+
+    fn id(id: &str) -> QuestionId {
+        QuestionId(id.to_string())
+    }
+
+    fn question(id: &str, tags: &[&str]) -> Question {
+        Question {
+            id: QuestionId(id.to_string()),
+            title: format!("Question {}", id),
+            content: "How do warp filters compose?".to_string(),
+            tags: Some(tags.iter().map(|tag| tag.to_string()).collect()),
+            metadata: Metadata::new("alice".to_string(), Utc::now()),
+            version: 1,
+        }
+    }
+
+    fn answer(id: &str, question_id: &str, content: &str) -> Answer {
+        Answer {
+            id: AnswerId(id.to_string()),
+            content: content.to_string(),
+            question_id: QuestionId(question_id.to_string()),
+            metadata: Metadata::new("bob".to_string(), Utc::now()),
+        }
+    }
+
+    fn edit(editor: &str) -> Edit {
+        Edit { editor: editor.to_string(), summary: None }
+    }
+
+    fn ids(questions: &[Question]) -> Vec<&str> {
+        questions.iter().map(|question| question.id.0.as_str()).collect()
+    }
+
+    fn tags(tags: &[&str]) -> Option<Vec<String>> {
+        Some(tags.iter().map(|tag| tag.to_string()).collect())
+    }
+
+    #[tokio::test]
+    async fn questions_and_answers_crud() {
+        for (name, store) in stores().await {
+            store.add_question(question("1", &["Rust"])).await.unwrap();
+            let stored = store.get_question(&id("1")).await.unwrap();
+            assert_eq!((stored.version, &stored.tags), (1, &tags(&["rust"])), "{}", name);
+            let duplicate = store.add_question(question("1", &[])).await;
+            assert!(matches!(duplicate, Err(Error::QuestionAlreadyExists(_))), "{}", name);
+
+            let mut update = question("1", &["rust"]);
+            update.title = "Updated".to_string();
+            let updated = store.update_question(&id("1"), update.clone(), &edit("bob"), &IfMatch::version(1)).await.unwrap();
+            assert_eq!((updated.title.as_str(), updated.version), ("Updated", 2), "{}", name);
+            let stale = store.update_question(&id("1"), update, &edit("bob"), &IfMatch::version(1)).await;
+            assert!(matches!(stale, Err(Error::PreconditionFailed)), "{}", name);
+
+            store.add_answer(answer("a1", "1", "first")).await.unwrap();
+            let duplicate = store.add_answer(answer("a1", "1", "again")).await;
+            assert!(matches!(duplicate, Err(Error::AnswerAlreadyExists(_))), "{}", name);
+            let unknown = store.add_answer(answer("a2", "missing", "orphan")).await;
+            assert!(matches!(unknown, Err(Error::UnknownQuestion(_))), "{}", name);
+            let updated = store.update_answer(&AnswerId("a1".to_string()), answer("a1", "1", "edited")).await.unwrap();
+            assert_eq!(updated.metadata.author, "bob", "{}", name);
+            assert_eq!(store.get_answer(&AnswerId("a1".to_string())).await.unwrap().content, "edited", "{}", name);
+            assert_eq!(store.count_answers_for_question(&id("1")).await.unwrap(), 1, "{}", name);
+            store.delete_answer(&AnswerId("a1".to_string())).await.unwrap();
+            let deleted = store.get_answer(&AnswerId("a1".to_string())).await;
+            assert!(matches!(deleted, Err(Error::AnswerNotFound)), "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn trash_and_restore() {
+        for (name, store) in stores().await {
+            store.add_question(question("1", &["rust"])).await.unwrap();
+            store.add_question(question("2", &["web"])).await.unwrap();
+            store.add_answer(answer("a1", "1", "first")).await.unwrap();
+            store.delete_question(&id("1"), "carol", &IfMatch::version(1)).await.unwrap();
+
+            assert!(matches!(store.get_question(&id("1")).await, Err(Error::QuestionNotFound)), "{}", name);
+            let answer_result = store.get_answer(&AnswerId("a1".to_string())).await;
+            assert!(matches!(answer_result, Err(Error::AnswerNotFound)), "{}", name);
+            let trashed_answer = store.add_answer(answer("a2", "1", "late")).await;
+            assert!(matches!(trashed_answer, Err(Error::UnknownQuestion(_))), "{}", name);
+            let taken = store.add_question(question("1", &[])).await;
+            assert!(matches!(taken, Err(Error::QuestionAlreadyExists(_))), "{}", name);
+            let all = store.get_questions(SortOrder::Created, None, None).await.unwrap();
+            assert_eq!(ids(&all), ["2"], "{}", name);
+            let trash = store.get_deleted_questions().await.unwrap();
+            assert_eq!(trash.iter().map(|q| q.deleted_by.as_str()).collect::<Vec<_>>(), ["carol"], "{}", name);
+
+            store.restore_question(&id("1")).await.unwrap();
+            assert_eq!(store.get_answer(&AnswerId("a1".to_string())).await.unwrap().content, "first", "{}", name);
+            let all = store.get_questions(SortOrder::Created, None, None).await.unwrap();
+            assert_eq!(ids(&all), ["1", "2"], "{}", name);
+            assert!(store.get_deleted_questions().await.unwrap().is_empty(), "{}", name);
+            assert!(matches!(store.restore_question(&id("1")).await, Err(Error::QuestionNotFound)), "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn tag_rename_reaches_trashed_questions() {
+        for (name, store) in stores().await {
+            store.add_question(question("1", &["rust", "warp"])).await.unwrap();
+            store.add_question(question("2", &["warp"])).await.unwrap();
+            store.delete_question(&id("1"), "carol", &IfMatch(None)).await.unwrap();
+
+            assert_eq!(store.rename_tag("warp", "warp-rs", "dave").await.unwrap(), 2, "{}", name);
+            store.delete_question(&id("2"), "carol", &IfMatch(None)).await.unwrap();
+            assert_eq!(store.rename_tag("rust", "rust-lang", "dave").await.unwrap(), 1, "{}", name);
+            assert!(store.get_tags().await.unwrap().is_empty(), "{}", name);
+
+            let restored = store.restore_question(&id("1")).await.unwrap();
+            assert_eq!((restored.version, &restored.tags), (3, &tags(&["rust-lang", "warp-rs"])), "{}", name);
+            let latest = store.get_revision(&id("1"), 3).await.unwrap();
+            assert_eq!((latest.editor.as_str(), &latest.tags), ("dave", &restored.tags), "{}", name);
+            let unused = store.rename_tag("warp", "web", "dave").await;
+            assert!(matches!(unused, Err(Error::TagNotFound(_))), "{}", name);
+
+            store.set_tag_alias("rs", "rust-lang", "dave").await.unwrap();
+            let aliased = store.add_question(question("3", &["RS"])).await.unwrap();
+            assert_eq!(aliased.tags, tags(&["rust-lang"]), "{}", name);
+            let filter = TagFilter { tags: vec!["rs".to_string()], mode: Default::default() };
+            assert_eq!(store.count_questions(Some(&filter)).await.unwrap(), 2, "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn pagination_follows_sort_order() {
+        for (name, store) in stores().await {
+            for question_id in ["b", "c", "a", "d"] {
+                store.add_question(question(question_id, &[])).await.unwrap();
+            }
+            let created = store.get_questions(SortOrder::Created, None, None).await.unwrap();
+            assert_eq!(ids(&created), ["b", "c", "a", "d"], "{}", name);
+            let by_id = store.get_questions(SortOrder::Id, None, Some(Pagination { start: 1, end: 3 })).await.unwrap();
+            assert_eq!(ids(&by_id), ["b", "c"], "{}", name);
+            let beyond = store.get_questions(SortOrder::Id, None, Some(Pagination { start: 10, end: 20 })).await.unwrap();
+            assert!(beyond.is_empty(), "{}", name);
+
+            let first = CursorPagination { after: None, limit: 3 };
+            let (page, next) = store.get_questions_after(SortOrder::Created, None, first).await.unwrap();
+            assert_eq!(ids(&page), ["b", "c", "a"], "{}", name);
+            let second = CursorPagination { after: next, limit: 3 };
+            let (page, next) = store.get_questions_after(SortOrder::Created, None, second).await.unwrap();
+            assert_eq!((ids(&page), next), (vec!["d"], None), "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn every_change_records_a_revision() {
+        for (name, store) in stores().await {
+            store.add_question(question("1", &["rust"])).await.unwrap();
+            let mut update = question("1", &["rust"]);
+            update.title = "Second".to_string();
+            let summary = Edit { editor: "bob".to_string(), summary: Some("retitle".to_string()) };
+            store.update_question(&id("1"), update, &summary, &IfMatch(None)).await.unwrap();
+            store.merge_tags(&["rust".to_string()], "rust-lang", "carol").await.unwrap();
+
+            let revisions = store.get_revisions(&id("1")).await.unwrap();
+            let history: Vec<_> = revisions
+                .iter()
+                .map(|r| (r.number, r.editor.as_str(), r.title.as_str(), r.summary.as_deref()))
+                .collect();
+            assert_eq!(
+                history,
+                [
+                    (1, "alice", "Question 1", None),
+                    (2, "bob", "Second", Some("retitle")),
+                    (3, "carol", "Second", Some("renamed tag 'rust' to 'rust-lang'")),
+                ],
+                "{}",
+                name
+            );
+            assert_eq!(store.get_question(&id("1")).await.unwrap().version, 3, "{}", name);
+            let missing = store.get_revision(&id("1"), 9).await;
+            assert!(matches!(missing, Err(Error::RevisionNotFound(9))), "{}", name);
+        }
+    }
+}
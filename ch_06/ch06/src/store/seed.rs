@@ -0,0 +1,142 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+
+use chrono::Utc;
+use handle_errors::Error;
+use serde::de::{self, Deserializer, MapAccess, Visitor};
+use serde::Deserialize;
+
+use crate::store::Repository;
+use crate::types::pagination::{Pagination, SortOrder};
+use crate::Question;
+
+// 支持两种格式：
+// 1. 以 id 为键的对象，即 question.json 的格式 { "1": { "id": "1", ... } }
+// 2. Question 数组 [ { "id": "1", ... } ]
+pub fn load_seed(path: &Path) -> Result<Vec<Question>, Error> {
+    let file = std::fs::read_to_string(path).map_err(Error::SeedReadError)?;
+    parse_seed(&file)
+}
+
+fn parse_seed(file: &str) -> Result<Vec<Question>, Error> {
+    // 先看第一个非空白字符决定格式，这样 serde_json 报错时能保留行号和列号
+    let questions = match file.trim_start().chars().next() {
+        Some('[') => serde_json::from_str::<Vec<Question>>(file).map_err(seed_parse_error)?,
+        _ => serde_json::from_str::<SeedObject>(file).map_err(seed_parse_error)?.0,
+    };
+    Ok(questions)
+}
+
+// 对象格式中的问题，保持文件中的顺序（决定导入后的创建顺序）；键必须与问题的 id 一致，不能重复
+struct SeedObject(Vec<Question>);
+
+impl<'de> Deserialize<'de> for SeedObject {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SeedVisitor;
+
+        impl<'de> Visitor<'de> for SeedVisitor {
+            type Value = SeedObject;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an object of questions keyed by id or an array of questions")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<SeedObject, A::Error> {
+                let mut questions = Vec::with_capacity(map.size_hint().unwrap_or(0));
+                let mut keys = HashSet::new();
+                while let Some((key, question)) = map.next_entry::<String, Question>()? {
+                    if key != question.id.0 {
+                        let message = format!("key '{}' does not match question id '{}'", key, question.id.0);
+                        return Err(de::Error::custom(message));
+                    }
+                    if !keys.insert(key) {
+                        return Err(de::Error::custom(format!("duplicate question id '{}'", question.id.0)));
+                    }
+                    questions.push(question);
+                }
+                Ok(SeedObject(questions))
+            }
+        }
+
+        deserializer.deserialize_map(SeedVisitor)
+    }
+}
+
+fn seed_parse_error(err: serde_json::Error) -> Error {
+    // serde_json 的错误信息末尾自带位置，去掉后单独放到 line/column 中
+    let position = format!(" at line {} column {}", err.line(), err.column());
+    let message = err.to_string();
+    Error::SeedParseError {
+        line: err.line(),
+        column: err.column(),
+        message: message.strip_suffix(&position).unwrap_or(&message).to_string(),
+    }
+}
+
+// 只在存储为空时写入种子数据，避免重启后覆盖已有的修改；回收站中的问题也算，它们仍然占用 id
+pub async fn seed_store(store: &dyn Repository, questions: Vec<Question>) -> Result<usize, Error> {
+    let existing = store.get_questions(SortOrder::default(), None, Some(Pagination { start: 0, end: 1 })).await?;
+    if !existing.is_empty() || !store.get_deleted_questions().await?.is_empty() {
+        return Ok(0);
+    }
+
+    // 旧的种子数据没有时间和作者，导入时补上
+    let now = Utc::now();
+    let count = questions.len();
+    for mut question in questions {
+        question.metadata.fill_missing(now);
+        store.add_question(question).await?;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(questions: &[Question]) -> Vec<&str> {
+        questions.iter().map(|question| question.id.0.as_str()).collect()
+    }
+
+    fn parse_error(file: &str) -> (usize, String) {
+        match parse_seed(file) {
+            Err(Error::SeedParseError { line, message, .. }) => (line, message),
+            other => panic!("expected SeedParseError, got {:?}", other.map(|questions| questions.len())),
+        }
+    }
+
+    #[test]
+    fn both_formats_keep_file_order() {
+        let object = r#"{
+            "q2": { "id": "q2", "title": "Second", "content": "B", "tags": ["web"] },
+            "q10": { "id": "q10", "title": "Tenth", "content": "J" },
+            "q1": { "id": "q1", "title": "First", "content": "A", "tags": ["rust"] }
+        }"#;
+        let questions = parse_seed(object).unwrap();
+        assert_eq!(ids(&questions), ["q2", "q10", "q1"]);
+        assert_eq!(questions[0].tags, Some(vec!["web".to_string()]));
+
+        let array = r#"
+            [ { "id": "q3", "title": "Third", "content": "C" }, { "id": "q1", "title": "First", "content": "A" } ]"#;
+        assert_eq!(ids(&parse_seed(array).unwrap()), ["q3", "q1"]);
+        assert!(parse_seed("{}").unwrap().is_empty());
+    }
+
+    #[test]
+    fn object_keys_must_match_ids() {
+        let mismatch = "{\n  \"q1\": { \"id\": \"q2\", \"title\": \"T\", \"content\": \"C\" }\n}";
+        assert_eq!(parse_error(mismatch).1, "key 'q1' does not match question id 'q2'");
+
+        let duplicate = r#"{ "q1": { "id": "q1", "title": "T", "content": "C" },
+                             "q1": { "id": "q1", "title": "T", "content": "C" } }"#;
+        assert_eq!(parse_error(duplicate).1, "duplicate question id 'q1'");
+    }
+
+    #[test]
+    fn syntax_errors_report_their_position() {
+        let (line, message) = parse_error("[\n  { \"id\": \"q1\", \"title\": }\n]");
+        assert_eq!(line, 2);
+        assert!(message.starts_with("expected value"), "{}", message);
+    }
+}
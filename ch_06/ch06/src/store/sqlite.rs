@@ -0,0 +1,910 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use handle_errors::Error;
+use sqlx::query::Query;
+use sqlx::sqlite::{Sqlite, SqliteArguments, SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteRow};
+use sqlx::{Row, SqliteConnection, Transaction};
+use tokio::sync::RwLock;
+
+use crate::search::SearchIndex;
+use crate::types::etag::IfMatch;
+use crate::types::metadata::Metadata;
+use crate::store::{AnswerRepository, QuestionRepository, TagRepository};
+use crate::types::pagination::{Cursor, CursorPagination, Pagination, SortOrder};
+use crate::types::question::DeletedQuestion;
+use crate::types::revision::{Edit, Revision};
+use crate::types::tag::{merge_map, required_tag, resolve_tags, TagAlias, TagCount, TagFilter, TagMatch};
+use crate::{Answer, AnswerId, Question, QuestionId};
+
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone)]
+pub struct SqliteStore {
+    pool: SqlitePool,
+    // 全文索引保存在进程内，启动时从数据库加载；修改问题时持有写锁直到事务提交，保证与数据库一致
+    search: Arc<RwLock<SearchIndex>>,
+}
+
+impl SqliteStore {
+    pub async fn new(db_url: &str) -> Result<Self, Error> {
+        let options = SqliteConnectOptions::from_str(db_url)
+            .map_err(Error::DatabaseQueryError)?
+            .create_if_missing(true)
+            // 其他连接持有写锁时等待，而不是立即返回 SQLITE_BUSY
+            .busy_timeout(BUSY_TIMEOUT);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect_with(options)
+            .await
+            .map_err(Error::DatabaseQueryError)?;
+
+        // 启动时执行 migrations 目录下尚未执行过的迁移
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await
+            .map_err(|e| Error::DatabaseQueryError(e.into()))?;
+
+        let questions = sqlx::query("SELECT id, title, content, tags, created_at, updated_at, author, version FROM live_questions")
+            .map(question_from_row)
+            .fetch_all(&pool)
+            .await
+            .map_err(Error::DatabaseQueryError)?;
+        let search = SearchIndex::build(questions.iter().map(|q| (&q.id, q)));
+
+        Ok(SqliteStore {
+            pool,
+            search: Arc::new(RwLock::new(search)),
+        })
+    }
+
+    // 写事务一开始就获取写锁。默认的 BEGIN 先读后写，读锁升级为写锁失败时会直接返回 SQLITE_BUSY，
+    // 不会等待 busy_timeout；并发写入时会变成 500
+    async fn begin(&self) -> Result<Transaction<'static, Sqlite>, Error> {
+        self.pool.begin_with("BEGIN IMMEDIATE").await.map_err(Error::DatabaseQueryError)
+    }
+
+    // 过滤条件中的别名替换成对应的标签
+    async fn resolve_filter(&self, tags: Option<&TagFilter>) -> Result<Option<TagFilter>, Error> {
+        let Some(filter) = tags else {
+            return Ok(None);
+        };
+        let mut conn = self.pool.acquire().await.map_err(Error::DatabaseQueryError)?;
+        let aliases = load_aliases(&mut conn).await.map_err(Error::DatabaseQueryError)?;
+        Ok(Some(filter.resolve(&aliases)))
+    }
+}
+
+// start/end 转换成 LIMIT/OFFSET，没有分页时 LIMIT -1 表示不限制
+fn limit_offset(pagination: Option<Pagination>) -> (i64, i64) {
+    match pagination {
+        None => (-1, 0),
+        Some(p) => (saturating_i64(p.limit()), saturating_i64(p.start)),
+    }
+}
+
+// 超出 i64 范围的页码和序号取 i64::MAX（结果为空页）；直接 as 转换会变成负数，SQLite 把负的 OFFSET 当作 0
+fn saturating_i64<T: TryInto<i64>>(value: T) -> i64 {
+    value.try_into().unwrap_or(i64::MAX)
+}
+
+// 查询时多取了一条，有多余的一条说明还有下一页，游标指向本页最后一条
+fn split_page<T>(mut rows: Vec<(Cursor, T)>, limit: usize) -> (Vec<T>, Option<Cursor>) {
+    let has_more = rows.len() > limit;
+    rows.truncate(limit);
+    let next = match has_more {
+        true => rows.last().map(|(cursor, _)| cursor.clone()),
+        false => None,
+    };
+    (rows.into_iter().map(|(_, item)| item).collect(), next)
+}
+
+fn encode_tags(tags: &Option<Vec<String>>) -> Option<String> {
+    tags.as_ref().map(|tags| serde_json::to_string(tags).unwrap_or_default())
+}
+
+// 按标签过滤的条件，没有过滤时恒为真；对应的参数由 bind_tags 绑定
+fn tag_condition(tags: Option<&TagFilter>) -> String {
+    let Some(filter) = tags else {
+        return "1".to_string();
+    };
+    let placeholders = vec!["?"; filter.tags.len()].join(", ");
+    match filter.mode {
+        TagMatch::Any => format!(
+            "id IN (SELECT question_id FROM question_tags WHERE tag IN ({}))",
+            placeholders
+        ),
+        // filter.tags 已去重，命中的标签数等于过滤的标签数说明全部包含
+        TagMatch::All => format!(
+            "id IN (SELECT question_id FROM question_tags WHERE tag IN ({}) GROUP BY question_id HAVING COUNT(*) = ?)",
+            placeholders
+        ),
+    }
+}
+
+fn bind_tags<'q>(
+    mut query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    tags: Option<&'q TagFilter>,
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    if let Some(filter) = tags {
+        for tag in &filter.tags {
+            query = query.bind(tag);
+        }
+        if filter.mode == TagMatch::All {
+            query = query.bind(filter.tags.len() as i64);
+        }
+    }
+    query
+}
+
+// 用问题当前的标签重建 question_tags 中的索引行
+async fn write_tags(
+    conn: &mut SqliteConnection,
+    id: &QuestionId,
+    tags: &Option<Vec<String>>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM question_tags WHERE question_id = ?")
+        .bind(&id.0)
+        .execute(&mut *conn)
+        .await?;
+    for tag in tags.iter().flatten() {
+        sqlx::query("INSERT OR IGNORE INTO question_tags (question_id, tag) VALUES (?, ?)")
+            .bind(&id.0)
+            .bind(tag)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+async fn load_aliases(conn: &mut SqliteConnection) -> Result<BTreeMap<String, String>, sqlx::Error> {
+    let aliases = sqlx::query("SELECT alias, tag FROM tag_aliases")
+        .map(|row: SqliteRow| (row.get("alias"), row.get("tag")))
+        .fetch_all(conn)
+        .await?;
+    Ok(aliases.into_iter().collect())
+}
+
+// 按 rename（旧标签 → 新标签）替换问题中的标签，并让指向旧标签的别名改为指向新标签；
+// 每个被修改的问题版本号加 1，并以 editor 的名义记录一个历史版本。返回修改的问题数
+async fn rename_tags(
+    conn: &mut SqliteConnection,
+    rename: &BTreeMap<String, String>,
+    editor: &str,
+) -> Result<usize, sqlx::Error> {
+    if rename.is_empty() {
+        return Ok(0);
+    }
+    for (from, to) in rename {
+        sqlx::query("UPDATE tag_aliases SET tag = ? WHERE tag = ?")
+            .bind(to)
+            .bind(from)
+            .execute(&mut *conn)
+            .await?;
+    }
+    let sql = format!(
+        "SELECT id, title, content, tags, created_at, updated_at, author, version FROM questions
+         WHERE id IN (SELECT question_id FROM question_tags WHERE tag IN ({}))",
+        vec!["?"; rename.len()].join(", ")
+    );
+    let mut query = sqlx::query(&sql);
+    for from in rename.keys() {
+        query = query.bind(from);
+    }
+    let questions = query.map(question_from_row).fetch_all(&mut *conn).await?;
+
+    let now = Utc::now();
+    for question in &questions {
+        let edit = Edit::rename_tags(editor, rename, &question.tags);
+        let mut question = question.clone();
+        question.tags = resolve_tags(question.tags, rename);
+        question.metadata.updated_at = now;
+        question.version += 1;
+        sqlx::query("UPDATE questions SET tags = ?, updated_at = ?, version = ? WHERE id = ?")
+            .bind(encode_tags(&question.tags))
+            .bind(encode_time(&now))
+            .bind(question.version as i64)
+            .bind(&question.id.0)
+            .execute(&mut *conn)
+            .await?;
+        write_tags(conn, &question.id, &question.tags).await?;
+        let number = next_revision(conn, &question.id).await?;
+        write_revision(conn, &Revision::new(number, &question.id, &question, &edit)).await?;
+    }
+    Ok(questions.len())
+}
+
+// 修改前读取的版本号；随后的 UPDATE 带上 version = ? 条件，期间被其他请求修改时不会覆盖
+async fn current_version(conn: &mut SqliteConnection, id: &QuestionId) -> Result<u64, Error> {
+    let version: Option<i64> = sqlx::query_scalar("SELECT version FROM live_questions WHERE id = ?")
+        .bind(&id.0)
+        .fetch_optional(conn)
+        .await
+        .map_err(Error::DatabaseQueryError)?;
+    version.map(|version| version as u64).ok_or(Error::QuestionNotFound)
+}
+
+// 回收站中的问题不能再添加回答；在写回答的事务中检查，期间问题不会被移到回收站
+async fn check_question_live(conn: &mut SqliteConnection, id: &QuestionId) -> Result<(), Error> {
+    let live: Option<i64> = sqlx::query_scalar("SELECT 1 FROM live_questions WHERE id = ?")
+        .bind(&id.0)
+        .fetch_optional(conn)
+        .await
+        .map_err(Error::DatabaseQueryError)?;
+    live.map(|_| ()).ok_or_else(|| Error::UnknownQuestion(id.0.clone()))
+}
+
+// answers.question_id 的外键约束失败说明引用的问题不存在，主键冲突说明 id 已被使用
+fn answer_write_error(err: sqlx::Error, answer: &Answer) -> Error {
+    match &err {
+        sqlx::Error::Database(db_err) if db_err.is_foreign_key_violation() => {
+            Error::UnknownQuestion(answer.question_id.0.clone())
+        }
+        sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+            Error::AnswerAlreadyExists(answer.id.0.clone())
+        }
+        _ => Error::DatabaseQueryError(err),
+    }
+}
+
+// 时间以 RFC 3339 字符串保存
+fn encode_time(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339()
+}
+
+fn decode_time(value: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .unwrap_or_default()
+}
+
+fn metadata_from_row(row: &SqliteRow) -> Metadata {
+    let time = |column: &str| {
+        let value: String = row.get(column);
+        decode_time(&value)
+    };
+    Metadata {
+        created_at: time("created_at"),
+        updated_at: time("updated_at"),
+        author: row.get("author"),
+    }
+}
+
+async fn next_revision(conn: &mut SqliteConnection, id: &QuestionId) -> Result<u64, sqlx::Error> {
+    let number: i64 = sqlx::query_scalar("SELECT IFNULL(MAX(number), 0) + 1 FROM question_revisions WHERE question_id = ?")
+        .bind(&id.0)
+        .fetch_one(conn)
+        .await?;
+    Ok(number as u64)
+}
+
+async fn write_revision(conn: &mut SqliteConnection, revision: &Revision) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO question_revisions (question_id, number, title, content, tags, editor, created_at, summary)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&revision.question_id.0)
+    .bind(revision.number as i64)
+    .bind(&revision.title)
+    .bind(&revision.content)
+    .bind(encode_tags(&revision.tags))
+    .bind(&revision.editor)
+    .bind(encode_time(&revision.created_at))
+    .bind(&revision.summary)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+fn revision_from_row(row: SqliteRow) -> Revision {
+    let tags: Option<String> = row.get("tags");
+    let number: i64 = row.get("number");
+    let created_at: String = row.get("created_at");
+    Revision {
+        number: number as u64,
+        question_id: QuestionId(row.get("question_id")),
+        title: row.get("title"),
+        content: row.get("content"),
+        tags: tags.and_then(|tags| serde_json::from_str(&tags).ok()),
+        editor: row.get("editor"),
+        created_at: decode_time(&created_at),
+        summary: row.get("summary"),
+    }
+}
+
+fn question_from_row(row: SqliteRow) -> Question {
+    let tags: Option<String> = row.get("tags");
+    Question {
+        id: QuestionId(row.get("id")),
+        title: row.get("title"),
+        content: row.get("content"),
+        tags: tags.and_then(|tags| serde_json::from_str(&tags).ok()),
+        metadata: metadata_from_row(&row),
+        version: row.get::<i64, _>("version") as u64,
+    }
+}
+
+fn answer_from_row(row: SqliteRow) -> Answer {
+    Answer {
+        id: AnswerId(row.get("id")),
+        content: row.get("content"),
+        question_id: QuestionId(row.get("question_id")),
+        metadata: metadata_from_row(&row),
+    }
+}
+
+#[async_trait]
+impl QuestionRepository for SqliteStore {
+    async fn get_questions(
+        &self,
+        sort: SortOrder,
+        tags: Option<&TagFilter>,
+        pagination: Option<Pagination>,
+    ) -> Result<Vec<Question>, Error> {
+        let tags = self.resolve_filter(tags).await?;
+        let tags = tags.as_ref();
+        let (limit, offset) = limit_offset(pagination);
+        // 排序字段只能是下面两个固定的列名，不会拼接用户输入
+        let order_by = match sort {
+            SortOrder::Created => "created_seq",
+            SortOrder::Id => "id",
+        };
+        let sql = format!(
+            "SELECT id, title, content, tags, created_at, updated_at, author, version FROM live_questions WHERE {} ORDER BY {} LIMIT ? OFFSET ?",
+            tag_condition(tags),
+            order_by
+        );
+        bind_tags(sqlx::query(&sql), tags)
+            .bind(limit)
+            .bind(offset)
+            .map(question_from_row)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(Error::DatabaseQueryError)
+    }
+
+    async fn count_questions(&self, tags: Option<&TagFilter>) -> Result<usize, Error> {
+        let tags = self.resolve_filter(tags).await?;
+        let tags = tags.as_ref();
+        let sql = format!("SELECT COUNT(*) FROM live_questions WHERE {}", tag_condition(tags));
+        let count: i64 = bind_tags(sqlx::query(&sql), tags)
+            .map(|row: SqliteRow| row.get(0))
+            .fetch_one(&self.pool)
+            .await
+            .map_err(Error::DatabaseQueryError)?;
+        Ok(count as usize)
+    }
+
+    async fn get_questions_after(
+        &self,
+        sort: SortOrder,
+        tags: Option<&TagFilter>,
+        pagination: CursorPagination,
+    ) -> Result<(Vec<Question>, Option<Cursor>), Error> {
+        let tags = self.resolve_filter(tags).await?;
+        let tags = tags.as_ref();
+        // 多取一条用来判断是否还有下一页
+        let limit = pagination.limit as i64 + 1;
+        let rows: Vec<(Cursor, Question)> = match (sort, pagination.after) {
+            (SortOrder::Created, after @ (None | Some(Cursor::Created(_)))) => {
+                let after = match after {
+                    Some(Cursor::Created(seq)) => Some(saturating_i64(seq)),
+                    _ => None,
+                };
+                let sql = format!(
+                    "SELECT id, title, content, tags, created_at, updated_at, author, version, created_seq FROM live_questions
+                     WHERE (? IS NULL OR created_seq > ?) AND {} ORDER BY created_seq LIMIT ?",
+                    tag_condition(tags)
+                );
+                bind_tags(sqlx::query(&sql).bind(after).bind(after), tags)
+                    .bind(limit)
+                    .map(|row: SqliteRow| {
+                        let seq: i64 = row.get("created_seq");
+                        (Cursor::Created(seq as u64), question_from_row(row))
+                    })
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(Error::DatabaseQueryError)?
+            }
+            (SortOrder::Id, after @ (None | Some(Cursor::Id(_)))) => {
+                let after = match after {
+                    Some(Cursor::Id(id)) => Some(id),
+                    _ => None,
+                };
+                let sql = format!(
+                    "SELECT id, title, content, tags, created_at, updated_at, author, version FROM live_questions
+                     WHERE (? IS NULL OR id > ?) AND {} ORDER BY id LIMIT ?",
+                    tag_condition(tags)
+                );
+                bind_tags(sqlx::query(&sql).bind(&after).bind(&after), tags)
+                    .bind(limit)
+                    .map(|row: SqliteRow| {
+                        let question = question_from_row(row);
+                        (Cursor::Id(question.id.0.clone()), question)
+                    })
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(Error::DatabaseQueryError)?
+            }
+            _ => return Err(Error::InvalidCursor),
+        };
+        Ok(split_page(rows, pagination.limit))
+    }
+
+    async fn get_question(&self, id: &QuestionId) -> Result<Question, Error> {
+        sqlx::query("SELECT id, title, content, tags, created_at, updated_at, author, version FROM live_questions WHERE id = ?")
+            .bind(&id.0)
+            .map(question_from_row)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(Error::DatabaseQueryError)?
+            .ok_or(Error::QuestionNotFound)
+    }
+
+    async fn add_question(&self, mut question: Question) -> Result<Question, Error> {
+        question.version = 1;
+        let mut search = self.search.write().await;
+        // 问题和标签索引在同一个事务中写入
+        let mut tx = self.begin().await?;
+        let aliases = load_aliases(&mut tx).await.map_err(Error::DatabaseQueryError)?;
+        question.tags = resolve_tags(question.tags, &aliases);
+        sqlx::query(
+            "INSERT INTO questions (id, title, content, tags, created_at, updated_at, author, created_seq)
+             VALUES (?, ?, ?, ?, ?, ?, ?, (SELECT IFNULL(MAX(created_seq), 0) + 1 FROM questions))",
+        )
+        .bind(&question.id.0)
+        .bind(&question.title)
+        .bind(&question.content)
+        .bind(encode_tags(&question.tags))
+        .bind(encode_time(&question.metadata.created_at))
+        .bind(encode_time(&question.metadata.updated_at))
+        .bind(&question.metadata.author)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| match &e {
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+                Error::QuestionAlreadyExists(question.id.0.clone())
+            }
+            _ => Error::DatabaseQueryError(e),
+        })?;
+        write_tags(&mut tx, &question.id, &question.tags).await.map_err(Error::DatabaseQueryError)?;
+        write_revision(&mut tx, &Revision::initial(&question)).await.map_err(Error::DatabaseQueryError)?;
+        tx.commit().await.map_err(Error::DatabaseQueryError)?;
+        search.insert(&question.id, &question);
+        Ok(question)
+    }
+
+    async fn update_question(
+        &self,
+        id: &QuestionId,
+        mut question: Question,
+        edit: &Edit,
+        if_match: &IfMatch,
+    ) -> Result<Question, Error> {
+        let mut search = self.search.write().await;
+        let mut tx = self.begin().await?;
+        let version = current_version(&mut tx, id).await?;
+        if_match.check(version)?;
+        let aliases = load_aliases(&mut tx).await.map_err(Error::DatabaseQueryError)?;
+        question.tags = resolve_tags(question.tags, &aliases);
+        // 创建时间和作者不允许修改，返回数据库中原来的值
+        let row = sqlx::query(
+            "UPDATE questions SET title = ?, content = ?, tags = ?, updated_at = ?, version = version + 1
+             WHERE id = ? AND version = ? AND deleted_at IS NULL
+             RETURNING created_at, updated_at, author, version",
+        )
+        .bind(&question.title)
+        .bind(&question.content)
+        .bind(encode_tags(&question.tags))
+        .bind(encode_time(&question.metadata.updated_at))
+        .bind(&id.0)
+        .bind(version as i64)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(Error::DatabaseQueryError)?;
+        match row {
+            Some(row) => {
+                question.metadata = metadata_from_row(&row);
+                question.version = row.get::<i64, _>("version") as u64;
+            }
+            None => return Err(if_match.modified()),
+        }
+        write_tags(&mut tx, id, &question.tags).await.map_err(Error::DatabaseQueryError)?;
+        let number = next_revision(&mut tx, id).await.map_err(Error::DatabaseQueryError)?;
+        let revision = Revision::new(number, id, &question, edit);
+        write_revision(&mut tx, &revision).await.map_err(Error::DatabaseQueryError)?;
+        tx.commit().await.map_err(Error::DatabaseQueryError)?;
+        search.insert(id, &question);
+        Ok(question)
+    }
+
+    async fn delete_question(&self, id: &QuestionId, deleted_by: &str, if_match: &IfMatch) -> Result<(), Error> {
+        let mut search = self.search.write().await;
+        let mut tx = self.begin().await?;
+        let version = current_version(&mut tx, id).await?;
+        if_match.check(version)?;
+        // 只标记删除时间，回答和标签索引保留，恢复时不需要重建
+        let result = sqlx::query(
+            "UPDATE questions SET deleted_at = ?, deleted_by = ? WHERE id = ? AND version = ? AND deleted_at IS NULL",
+        )
+        .bind(encode_time(&Utc::now()))
+        .bind(deleted_by)
+        .bind(&id.0)
+        .bind(version as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(Error::DatabaseQueryError)?;
+        if result.rows_affected() == 0 {
+            return Err(if_match.modified());
+        }
+        tx.commit().await.map_err(Error::DatabaseQueryError)?;
+        search.remove(id);
+        Ok(())
+    }
+
+    async fn restore_question(&self, id: &QuestionId) -> Result<Question, Error> {
+        let mut search = self.search.write().await;
+        let question = sqlx::query(
+            "UPDATE questions SET deleted_at = NULL, deleted_by = NULL WHERE id = ? AND deleted_at IS NOT NULL
+             RETURNING id, title, content, tags, created_at, updated_at, author, version",
+        )
+        .bind(&id.0)
+        .map(question_from_row)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::DatabaseQueryError)?
+        .ok_or(Error::QuestionNotFound)?;
+        search.insert(id, &question);
+        Ok(question)
+    }
+
+    async fn get_deleted_questions(&self) -> Result<Vec<DeletedQuestion>, Error> {
+        sqlx::query(
+            "SELECT id, title, content, tags, created_at, updated_at, author, version, deleted_at, deleted_by FROM questions
+             WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC",
+        )
+        .map(|row: SqliteRow| {
+            let deleted_at: String = row.get("deleted_at");
+            let deleted_by: String = row.get("deleted_by");
+            DeletedQuestion {
+                deleted_at: decode_time(&deleted_at),
+                deleted_by,
+                question: question_from_row(row),
+            }
+        })
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::DatabaseQueryError)
+    }
+
+    async fn purge_deleted(&self, before: DateTime<Utc>) -> Result<usize, Error> {
+        let mut tx = self.begin().await?;
+        // 时间字符串的小数位数不固定，不能直接在 SQL 中比较，解析后再筛选
+        let deleted: Vec<(String, String)> =
+            sqlx::query_as("SELECT id, deleted_at FROM questions WHERE deleted_at IS NOT NULL")
+                .fetch_all(&mut *tx)
+                .await
+                .map_err(Error::DatabaseQueryError)?;
+        let mut count = 0;
+        for (id, deleted_at) in deleted {
+            if decode_time(&deleted_at) >= before {
+                continue;
+            }
+            // 回答、标签索引和历史版本通过外键 ON DELETE CASCADE 一并删除
+            sqlx::query("DELETE FROM questions WHERE id = ?")
+                .bind(&id)
+                .execute(&mut *tx)
+                .await
+                .map_err(Error::DatabaseQueryError)?;
+            count += 1;
+        }
+        tx.commit().await.map_err(Error::DatabaseQueryError)?;
+        Ok(count)
+    }
+
+    async fn get_revisions(&self, id: &QuestionId) -> Result<Vec<Revision>, Error> {
+        // 先确认问题存在，没有版本的问题返回空列表而不是 404
+        self.get_question(id).await?;
+        sqlx::query(
+            "SELECT question_id, number, title, content, tags, editor, created_at, summary
+             FROM question_revisions WHERE question_id = ? ORDER BY number",
+        )
+        .bind(&id.0)
+        .map(revision_from_row)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::DatabaseQueryError)
+    }
+
+    async fn get_revision(&self, id: &QuestionId, number: u64) -> Result<Revision, Error> {
+        self.get_question(id).await?;
+        sqlx::query(
+            "SELECT question_id, number, title, content, tags, editor, created_at, summary
+             FROM question_revisions WHERE question_id = ? AND number = ?",
+        )
+        .bind(&id.0)
+        .bind(number as i64)
+        .map(revision_from_row)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Error::DatabaseQueryError)?
+        .ok_or(Error::RevisionNotFound(number))
+    }
+
+    async fn search_questions(
+        &self,
+        query: &str,
+        pagination: Pagination,
+    ) -> Result<(Vec<(Question, f64)>, usize), Error> {
+        let results = self.search.read().await.search(query);
+        let mut page = Vec::with_capacity(pagination.limit());
+        for (id, score) in results.iter().skip(pagination.start).take(pagination.limit()) {
+            // 只查询当前页的问题；释放读锁之后问题可能刚好被删除，跳过即可
+            match self.get_question(id).await {
+                Ok(question) => page.push((question, *score)),
+                Err(Error::QuestionNotFound) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok((page, results.len()))
+    }
+}
+
+#[async_trait]
+impl AnswerRepository for SqliteStore {
+    async fn get_answers_for_question(
+        &self,
+        question_id: &QuestionId,
+        pagination: Option<Pagination>,
+    ) -> Result<Vec<Answer>, Error> {
+        let (limit, offset) = limit_offset(pagination);
+        sqlx::query(
+            "SELECT id, content, question_id, created_at, updated_at, author FROM answers WHERE question_id = ? ORDER BY id LIMIT ? OFFSET ?",
+        )
+        .bind(&question_id.0)
+        .bind(limit)
+        .bind(offset)
+        .map(answer_from_row)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::DatabaseQueryError)
+    }
+
+    async fn count_answers_for_question(&self, question_id: &QuestionId) -> Result<usize, Error> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM answers WHERE question_id = ?")
+            .bind(&question_id.0)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(Error::DatabaseQueryError)?;
+        Ok(count as usize)
+    }
+
+    async fn get_answers_for_question_after(
+        &self,
+        question_id: &QuestionId,
+        pagination: CursorPagination,
+    ) -> Result<(Vec<Answer>, Option<Cursor>), Error> {
+        let after = match pagination.after {
+            Some(Cursor::Id(id)) => Some(id),
+            None => None,
+            Some(_) => return Err(Error::InvalidCursor),
+        };
+        let rows = sqlx::query(
+            "SELECT id, content, question_id, created_at, updated_at, author FROM answers
+             WHERE question_id = ? AND (? IS NULL OR id > ?) ORDER BY id LIMIT ?",
+        )
+        .bind(&question_id.0)
+        .bind(&after)
+        .bind(&after)
+        .bind(pagination.limit as i64 + 1)
+        .map(|row: SqliteRow| {
+            let answer = answer_from_row(row);
+            (Cursor::Id(answer.id.0.clone()), answer)
+        })
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::DatabaseQueryError)?;
+        Ok(split_page(rows, pagination.limit))
+    }
+
+    async fn get_answer(&self, id: &AnswerId) -> Result<Answer, Error> {
+        sqlx::query(
+            "SELECT id, content, question_id, created_at, updated_at, author FROM answers
+             WHERE id = ? AND question_id IN (SELECT id FROM live_questions)",
+        )
+        .bind(&id.0)
+            .map(answer_from_row)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(Error::DatabaseQueryError)?
+            .ok_or(Error::AnswerNotFound)
+    }
+
+    async fn add_answer(&self, answer: Answer) -> Result<Answer, Error> {
+        let mut tx = self.begin().await?;
+        check_question_live(&mut tx, &answer.question_id).await?;
+        sqlx::query(
+            "INSERT INTO answers (id, content, question_id, created_at, updated_at, author) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&answer.id.0)
+        .bind(&answer.content)
+        .bind(&answer.question_id.0)
+        .bind(encode_time(&answer.metadata.created_at))
+        .bind(encode_time(&answer.metadata.updated_at))
+        .bind(&answer.metadata.author)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| answer_write_error(e, &answer))?;
+        tx.commit().await.map_err(Error::DatabaseQueryError)?;
+        Ok(answer)
+    }
+
+    async fn update_answer(&self, id: &AnswerId, mut answer: Answer) -> Result<Answer, Error> {
+        let mut tx = self.begin().await?;
+        check_question_live(&mut tx, &answer.question_id).await?;
+        let row = sqlx::query(
+            "UPDATE answers SET content = ?, question_id = ?, updated_at = ?
+             WHERE id = ? AND question_id IN (SELECT id FROM live_questions)
+             RETURNING created_at, updated_at, author",
+        )
+        .bind(&answer.content)
+        .bind(&answer.question_id.0)
+        .bind(encode_time(&answer.metadata.updated_at))
+        .bind(&id.0)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| answer_write_error(e, &answer))?;
+        let Some(row) = row else {
+            return Err(Error::AnswerNotFound);
+        };
+        tx.commit().await.map_err(Error::DatabaseQueryError)?;
+        answer.metadata = metadata_from_row(&row);
+        Ok(answer)
+    }
+
+    async fn delete_answer(&self, id: &AnswerId) -> Result<(), Error> {
+        let result = sqlx::query("DELETE FROM answers WHERE id = ? AND question_id IN (SELECT id FROM live_questions)")
+            .bind(&id.0)
+            .execute(&self.pool)
+            .await
+            .map_err(Error::DatabaseQueryError)?;
+        match result.rows_affected() {
+            0 => Err(Error::AnswerNotFound),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl TagRepository for SqliteStore {
+    async fn get_tags(&self) -> Result<Vec<TagCount>, Error> {
+        sqlx::query(
+            "SELECT tag, COUNT(*) AS count FROM question_tags WHERE question_id IN (SELECT id FROM live_questions)
+             GROUP BY tag ORDER BY count DESC, tag",
+        )
+        .map(|row: SqliteRow| {
+            let count: i64 = row.get("count");
+            TagCount { name: row.get("tag"), count: count as usize }
+        })
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::DatabaseQueryError)
+    }
+
+    async fn get_tag_aliases(&self) -> Result<Vec<TagAlias>, Error> {
+        sqlx::query("SELECT alias, tag FROM tag_aliases ORDER BY alias")
+            .map(|row: SqliteRow| TagAlias { alias: row.get("alias"), tag: row.get("tag") })
+            .fetch_all(&self.pool)
+            .await
+            .map_err(Error::DatabaseQueryError)
+    }
+
+    async fn set_tag_alias(&self, alias: &str, tag: &str, editor: &str) -> Result<TagAlias, Error> {
+        let alias = required_tag(alias)?;
+        let tag = required_tag(tag)?;
+        let mut tx = self.begin().await?;
+        let aliases = load_aliases(&mut tx).await.map_err(Error::DatabaseQueryError)?;
+        let tag = aliases.get(&tag).cloned().unwrap_or(tag);
+        if alias == tag {
+            return Err(Error::InvalidTagAlias(alias));
+        }
+        let rename = BTreeMap::from([(alias.clone(), tag.clone())]);
+        rename_tags(&mut tx, &rename, editor).await.map_err(Error::DatabaseQueryError)?;
+        sqlx::query("INSERT OR REPLACE INTO tag_aliases (alias, tag) VALUES (?, ?)")
+            .bind(&alias)
+            .bind(&tag)
+            .execute(&mut *tx)
+            .await
+            .map_err(Error::DatabaseQueryError)?;
+        tx.commit().await.map_err(Error::DatabaseQueryError)?;
+        Ok(TagAlias { alias, tag })
+    }
+
+    async fn delete_tag_alias(&self, alias: &str) -> Result<(), Error> {
+        let alias = required_tag(alias)?;
+        let result = sqlx::query("DELETE FROM tag_aliases WHERE alias = ?")
+            .bind(&alias)
+            .execute(&self.pool)
+            .await
+            .map_err(Error::DatabaseQueryError)?;
+        match result.rows_affected() {
+            0 => Err(Error::TagAliasNotFound(alias)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn rename_tag(&self, from: &str, to: &str, editor: &str) -> Result<usize, Error> {
+        let from = required_tag(from)?;
+        let to = required_tag(to)?;
+        let mut tx = self.begin().await?;
+        let aliases = load_aliases(&mut tx).await.map_err(Error::DatabaseQueryError)?;
+        let to = aliases.get(&to).cloned().unwrap_or(to);
+        let used: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM question_tags WHERE tag = ?")
+            .bind(&from)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(Error::DatabaseQueryError)?;
+        if used == 0 {
+            return Err(Error::TagNotFound(from));
+        }
+        if from == to {
+            return Ok(0);
+        }
+        let rename = BTreeMap::from([(from, to)]);
+        let count = rename_tags(&mut tx, &rename, editor).await.map_err(Error::DatabaseQueryError)?;
+        tx.commit().await.map_err(Error::DatabaseQueryError)?;
+        Ok(count)
+    }
+
+    async fn merge_tags(&self, tags: &[String], into: &str, editor: &str) -> Result<usize, Error> {
+        let tags = tags.iter().map(|tag| required_tag(tag)).collect::<Result<Vec<String>, Error>>()?;
+        let into = required_tag(into)?;
+        let mut tx = self.begin().await?;
+        let aliases = load_aliases(&mut tx).await.map_err(Error::DatabaseQueryError)?;
+        let into = aliases.get(&into).cloned().unwrap_or(into);
+        let rename = merge_map(tags, &into);
+        let count = rename_tags(&mut tx, &rename, editor).await.map_err(Error::DatabaseQueryError)?;
+        tx.commit().await.map_err(Error::DatabaseQueryError)?;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::tests::sqlite_store;
+    use crate::types::metadata::Metadata;
+
+    #[test]
+    fn large_pages_saturate_instead_of_wrapping() {
+        assert_eq!(limit_offset(None), (-1, 0));
+        assert_eq!(limit_offset(Some(Pagination { start: 10, end: 30 })), (20, 10));
+        let far = Pagination { start: usize::MAX - 5, end: usize::MAX };
+        assert_eq!(limit_offset(Some(far)), (5, i64::MAX));
+        assert_eq!(saturating_i64(u64::MAX), i64::MAX);
+    }
+
+    #[tokio::test]
+    async fn page_beyond_i64_is_empty() {
+        let store = sqlite_store().await;
+        let question = Question {
+            id: QuestionId("1".to_string()),
+            title: "First".to_string(),
+            content: "Content".to_string(),
+            tags: None,
+            metadata: Metadata::new("alice".to_string(), Utc::now()),
+            version: 1,
+        };
+        store.add_question(question).await.unwrap();
+        let far = Pagination { start: i64::MAX as usize + 1, end: usize::MAX };
+        let page = store.get_questions(SortOrder::Created, None, Some(far)).await.unwrap();
+        assert!(page.is_empty());
+        let after = CursorPagination { after: Some(Cursor::Created(u64::MAX)), limit: 10 };
+        let (page, _) = store.get_questions_after(SortOrder::Created, None, after).await.unwrap();
+        assert!(page.is_empty());
+    }
+}
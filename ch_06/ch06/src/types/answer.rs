@@ -1,12 +1,52 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use crate::QuestionId;
+use crate::types::metadata::Metadata;
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct AnswerId(pub String);
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Answer {
     pub id: AnswerId,
     pub content: String,
-    pub question_id: QuestionId
-}
\ No newline at end of file
+    pub question_id: QuestionId,
+    #[serde(flatten)]
+    pub metadata: Metadata,
+}
+
+// 新建或修改回答时客户端提交的内容，id 由服务端决定
+#[derive(Debug, Clone)]
+pub struct NewAnswer {
+    pub content: String,
+    pub question_id: QuestionId,
+}
+
+// JSON 和表单共用的请求体，缺少的字段为空字符串，由校验返回字段错误
+#[derive(Debug, Deserialize)]
+pub struct NewAnswerFields {
+    #[serde(default)]
+    content: String,
+    #[serde(default, rename = "questionId", alias = "question_id")]
+    question_id: String,
+}
+
+impl From<NewAnswerFields> for NewAnswer {
+    fn from(fields: NewAnswerFields) -> Self {
+        NewAnswer {
+            content: fields.content,
+            question_id: QuestionId(fields.question_id),
+        }
+    }
+}
+
+impl NewAnswer {
+    pub fn into_answer(self, id: AnswerId, author: String, now: DateTime<Utc>) -> Answer {
+        Answer {
+            id,
+            content: self.content,
+            question_id: self.question_id,
+            metadata: Metadata::new(author, now),
+        }
+    }
+}
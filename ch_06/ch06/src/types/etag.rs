@@ -0,0 +1,72 @@
+use handle_errors::Error;
+
+// 问题的 ETag 就是带引号的版本号，例如 "3"
+pub fn etag(version: u64) -> String {
+    format!("\"{}\"", version)
+}
+
+// If-Match / If-None-Match 请求头中的实体标签列表，* 匹配任意版本
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntityTags {
+    Any,
+    Tags(Vec<String>),
+}
+
+impl EntityTags {
+    pub fn parse(header: &str) -> Self {
+        if header.trim() == "*" {
+            return EntityTags::Any;
+        }
+        let tags = header
+            .split(',')
+            .map(|tag| tag.trim())
+            .filter(|tag| !tag.is_empty())
+            .map(|tag| tag.to_string())
+            .collect();
+        EntityTags::Tags(tags)
+    }
+
+    // If-Match 使用强比较，弱标签 W/"..." 不匹配任何版本
+    pub fn matches_strong(&self, version: u64) -> bool {
+        match self {
+            EntityTags::Any => true,
+            EntityTags::Tags(tags) => tags.iter().any(|tag| *tag == etag(version)),
+        }
+    }
+
+    // If-None-Match 使用弱比较，忽略 W/ 前缀
+    pub fn matches_weak(&self, version: u64) -> bool {
+        match self {
+            EntityTags::Any => true,
+            EntityTags::Tags(tags) => tags
+                .iter()
+                .any(|tag| tag.strip_prefix("W/").unwrap_or(tag) == etag(version)),
+        }
+    }
+}
+
+// 修改和删除前检查 If-Match，没有这个请求头时不检查
+#[derive(Debug, Clone, Default)]
+pub struct IfMatch(pub Option<EntityTags>);
+
+impl IfMatch {
+    // 只匹配指定的版本
+    pub fn version(version: u64) -> Self {
+        IfMatch(Some(EntityTags::Tags(vec![etag(version)])))
+    }
+
+    pub fn check(&self, version: u64) -> Result<(), Error> {
+        match &self.0 {
+            Some(tags) if !tags.matches_strong(version) => Err(Error::PreconditionFailed),
+            _ => Ok(()),
+        }
+    }
+
+    // 检查通过之后、写入之前问题被其他请求修改了
+    pub fn modified(&self) -> Error {
+        match self.0 {
+            Some(_) => Error::PreconditionFailed,
+            None => Error::Conflict("question was modified by another request, please retry".to_string()),
+        }
+    }
+}
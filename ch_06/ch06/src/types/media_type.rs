@@ -0,0 +1,16 @@
+// Content-Type 的 essence（type/subtype），忽略 ; charset=utf-8 之类的参数，并统一转成小写
+pub fn essence(content_type: &str) -> String {
+    content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn essence_drops_parameters_and_case() {
+        assert_eq!(essence("Application/JSON; charset=utf-8"), "application/json");
+        assert_eq!(essence(" application/merge-patch+json "), "application/merge-patch+json");
+        assert_eq!(essence(""), "");
+    }
+}
@@ -0,0 +1,40 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// 没有登录信息时使用的作者，迁移前的旧数据也使用这个值
+pub const ANONYMOUS: &str = "anonymous";
+
+// 问题和回答共有的元数据，由服务端维护，客户端提交的值会被忽略
+// 旧数据中没有这些字段，反序列化后是默认值，需要调用 fill_missing 补上
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Metadata {
+    #[serde(default)]
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub author: String,
+}
+
+impl Metadata {
+    pub fn new(author: String, now: DateTime<Utc>) -> Self {
+        Metadata {
+            created_at: now,
+            updated_at: now,
+            author,
+        }
+    }
+
+    // 缺少创建时间时使用 now，缺少修改时间时与创建时间相同
+    pub fn fill_missing(&mut self, now: DateTime<Utc>) {
+        if self.created_at == DateTime::<Utc>::default() {
+            self.created_at = now;
+        }
+        if self.updated_at == DateTime::<Utc>::default() {
+            self.updated_at = self.created_at;
+        }
+        if self.author.is_empty() {
+            self.author = ANONYMOUS.to_string();
+        }
+    }
+}
@@ -1,3 +1,10 @@
 pub mod answer;
+pub mod etag;
+pub mod media_type;
+pub mod metadata;
 pub mod question;
-pub mod pagination;
\ No newline at end of file
+pub mod pagination;
+pub mod patch;
+pub mod revision;
+pub mod tag;
+pub mod validation;
\ No newline at end of file
@@ -1,26 +1,428 @@
 use std::collections::HashMap;
+use std::str::FromStr;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use handle_errors::Error;
+use serde::{Deserialize, Serialize};
 
+// 没有指定 limit 时每页的数量
+pub const DEFAULT_LIMIT: usize = 20;
+// 每页数量的上限
+pub const MAX_LIMIT: usize = 100;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Pagination {
     pub start: usize,
     pub end: usize,
 }
 
-pub fn extract_pagination(params: HashMap<String, String>) -> Result<Pagination, Error> {
-    // 同时获取 start 和 end 参数
-    let start_str = params.get("start").ok_or(Error::MissingParameters)?;
-    let end_str = params.get("end").ok_or(Error::MissingParameters)?;
+impl Pagination {
+    pub fn limit(&self) -> usize {
+        self.end.saturating_sub(self.start)
+    }
+}
+
+// 列表响应的格式，默认返回带总数的 envelope，?format=array 返回原来的 JSON 数组
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PageFormat {
+    #[default]
+    Envelope,
+    Array,
+}
+
+impl FromStr for PageFormat {
+    type Err = Error;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "envelope" => Ok(PageFormat::Envelope),
+            "array" => Ok(PageFormat::Array),
+            _ => Err(Error::InvalidFormat(format.to_string())),
+        }
+    }
+}
+
+pub fn extract_format(params: &mut HashMap<String, String>) -> Result<PageFormat, Error> {
+    match params.remove("format") {
+        Some(format) => format.parse(),
+        None => Ok(PageFormat::default()),
+    }
+}
+
+// start/end 分页的响应
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: usize,
+    pub start: usize,
+    pub limit: usize,
+    pub next: Option<String>,
+    pub prev: Option<String>,
+}
+
+// 第一页、上一页、下一页和最后一页的地址，用于响应体和 Link 头
+#[derive(Debug)]
+pub struct PageLinks {
+    pub first: String,
+    pub prev: Option<String>,
+    pub next: Option<String>,
+    pub last: String,
+}
+
+impl PageLinks {
+    // path 是不带查询参数的地址，query 是需要原样保留的其他参数（如 sort=id）
+    pub fn new(path: &str, query: &[String], pagination: Pagination, total: usize) -> Self {
+        let limit = pagination.limit().max(1);
+        let url = |start: usize, end: usize| {
+            let mut params = query.to_vec();
+            params.push(format!("start={}", start));
+            params.push(format!("end={}", end));
+            format!("{}?{}", path, params.join("&"))
+        };
+
+        let prev = match pagination.start {
+            0 => None,
+            start => Some(url(start.saturating_sub(limit), start)),
+        };
+        let next = match pagination.end < total {
+            true => Some(url(pagination.end, pagination.end + limit)),
+            false => None,
+        };
+        // 最后一页与当前页对齐，沿着 next 一直翻页最终会到达这里
+        let last_start = if pagination.start < total {
+            pagination.start + (total - 1 - pagination.start) / limit * limit
+        } else {
+            total.saturating_sub(limit)
+        };
+
+        PageLinks {
+            first: url(0, limit),
+            prev,
+            next,
+            last: url(last_start, last_start + limit),
+        }
+    }
+
+    // RFC 8288 格式：<url>; rel="next", <url>; rel="prev"
+    pub fn header(&self) -> String {
+        let mut links = vec![format!("<{}>; rel=\"first\"", self.first)];
+        if let Some(prev) = &self.prev {
+            links.push(format!("<{}>; rel=\"prev\"", prev));
+        }
+        if let Some(next) = &self.next {
+            links.push(format!("<{}>; rel=\"next\"", next));
+        }
+        links.push(format!("<{}>; rel=\"last\"", self.last));
+        links.join(", ")
+    }
+}
+
+// 列表的排序方式，?sort=created（默认，按创建顺序）或 ?sort=id
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    Created,
+    Id,
+}
+
+impl SortOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortOrder::Created => "created",
+            SortOrder::Id => "id",
+        }
+    }
+}
+
+impl FromStr for SortOrder {
+    type Err = Error;
+
+    fn from_str(sort: &str) -> Result<Self, Self::Err> {
+        match sort {
+            "created" => Ok(SortOrder::Created),
+            "id" => Ok(SortOrder::Id),
+            _ => Err(Error::InvalidSort(sort.to_string())),
+        }
+    }
+}
+
+// 取出并解析 sort 参数，剩下的参数用于分页
+pub fn extract_sort(params: &mut HashMap<String, String>) -> Result<SortOrder, Error> {
+    match params.remove("sort") {
+        Some(sort) => sort.parse(),
+        None => Ok(SortOrder::default()),
+    }
+}
+
+// 游标记录上一页最后一条数据的排序键，客户端只把它当作不透明的字符串
+// 同一种游标之间可以比较大小，用于在内存中跳过 after 之前的数据
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Cursor {
+    // 按创建顺序时的序号
+    Created(u64),
+    // 按 id 排序时的 id
+    Id(String),
+}
+
+impl Cursor {
+    pub fn matches(&self, sort: SortOrder) -> bool {
+        matches!(
+            (self, sort),
+            (Cursor::Created(_), SortOrder::Created) | (Cursor::Id(_), SortOrder::Id)
+        )
+    }
+
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, Error> {
+        let bytes = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| Error::InvalidCursor)?;
+        serde_json::from_slice(&bytes).map_err(|_| Error::InvalidCursor)
+    }
+}
+
+#[derive(Debug)]
+pub struct CursorPagination {
+    pub after: Option<Cursor>,
+    pub limit: usize,
+}
+
+// 游标分页的响应
+#[derive(Debug, Serialize)]
+pub struct CursorPage<T> {
+    pub items: Vec<T>,
+    // 没有下一页时为 null
+    pub next_cursor: Option<String>,
+}
+
+impl<T> CursorPage<T> {
+    pub fn new(items: Vec<T>, next: Option<Cursor>) -> Self {
+        CursorPage {
+            items,
+            next_cursor: next.map(|cursor| cursor.encode()),
+        }
+    }
+}
+
+// 每页数量的默认值和上限，可以通过 --default-page-size 和 --max-page-size 配置
+#[derive(Debug, Clone, Copy)]
+pub struct PageLimits {
+    pub default_limit: usize,
+    pub max_limit: usize,
+}
+
+impl Default for PageLimits {
+    fn default() -> Self {
+        PageLimits {
+            default_limit: DEFAULT_LIMIT,
+            max_limit: MAX_LIMIT,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PageRequest {
+    Offset(Pagination),
+    Cursor(CursorPagination),
+}
+
+// 列表接口允许的查询参数，其余参数一律拒绝
+pub const PAGE_PARAMS: &[&str] = &["start", "end", "limit", "cursor", "format"];
+
+pub fn check_params(params: &HashMap<String, String>, allowed: &[&str]) -> Result<(), Error> {
+    match params.keys().find(|key| !allowed.contains(&key.as_str())) {
+        Some(key) => Err(Error::UnknownParameter(key.to_string())),
+        None => Ok(()),
+    }
+}
+
+// 带有 cursor 参数时使用游标分页，空的 cursor= 表示从第一页开始；
+// 否则使用 start/end 分页，start 默认为 0，没有 end 时取 start + limit
+pub fn extract_page_request(params: &HashMap<String, String>, limits: PageLimits) -> Result<PageRequest, Error> {
+    let start = parse_param(params, "start")?;
+    let end = parse_param(params, "end")?;
+    let limit = parse_param(params, "limit")?;
+
+    if limit.is_some_and(|limit| limit == 0 || limit > limits.max_limit) {
+        return Err(Error::InvalidLimit(limits.max_limit));
+    }
+
+    if let Some(cursor) = params.get("cursor") {
+        for key in ["start", "end"] {
+            if params.contains_key(key) {
+                return Err(Error::ConflictingParameters(key.to_string(), "cursor".to_string()));
+            }
+        }
+        let after = match cursor.is_empty() {
+            true => None,
+            false => Some(Cursor::decode(cursor)?),
+        };
+        return Ok(PageRequest::Cursor(CursorPagination {
+            after,
+            limit: limit.unwrap_or(limits.default_limit),
+        }));
+    }
+
+    let start = start.unwrap_or(0);
+    let end = match (end, limit) {
+        (Some(_), Some(_)) => {
+            return Err(Error::ConflictingParameters("end".to_string(), "limit".to_string()));
+        }
+        (Some(end), None) => end,
+        (None, limit) => start.saturating_add(limit.unwrap_or(limits.default_limit)),
+    };
+    if start > end {
+        return Err(Error::InvalidRange);
+    }
+    if end - start > limits.max_limit {
+        return Err(Error::InvalidLimit(limits.max_limit));
+    }
+
+    Ok(PageRequest::Offset(Pagination { start, end }))
+}
+
+fn parse_param(params: &HashMap<String, String>, key: &str) -> Result<Option<usize>, Error> {
+    params
+        .get(key)
+        .map(|value| value.parse::<usize>().map_err(Error::ParseError))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(query: &[(&str, &str)]) -> Result<PageRequest, Error> {
+        let params = query.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        extract_page_request(&params, PageLimits::default())
+    }
+
+    fn offset(query: &[(&str, &str)]) -> (usize, usize) {
+        match request(query) {
+            Ok(PageRequest::Offset(p)) => (p.start, p.end),
+            other => panic!("expected offset pagination, got {:?}", other),
+        }
+    }
+
+    fn links(start: usize, end: usize, total: usize) -> PageLinks {
+        PageLinks::new("/questions", &["sort=id".to_string()], Pagination { start, end }, total)
+    }
+
+    fn range(url: &str) -> (usize, usize) {
+        let query = url.split_once('?').unwrap().1;
+        let param = |key: &str| {
+            let prefix = format!("{}=", key);
+            query.split('&').find_map(|p| p.strip_prefix(prefix.as_str())).unwrap().parse().unwrap()
+        };
+        (param("start"), param("end"))
+    }
+
+    #[test]
+    fn page_links_on_aligned_pages() {
+        let page = links(0, 10, 25);
+        assert_eq!(page.first, "/questions?sort=id&start=0&end=10");
+        assert_eq!(page.prev, None);
+        assert_eq!(page.next.as_deref().map(range), Some((10, 20)));
+        assert_eq!(range(&page.last), (20, 30));
+
+        let page = links(20, 30, 25);
+        assert_eq!(page.prev.as_deref().map(range), Some((10, 20)));
+        assert_eq!(page.next, None);
+        assert_eq!(range(&page.last), (20, 30));
 
-    // 解析参数
-    let start = start_str.parse::<usize>().map_err(Error::ParseError)?;
-    let end = end_str.parse::<usize>().map_err(Error::ParseError)?;
+        // total 正好是每页数量的整数倍时，最后一页不是空页
+        assert_eq!(range(&links(0, 10, 20).last), (10, 20));
+    }
 
-    // （可选）可以在这里就检查 start < end
-    // if start >= end {
-    //     return Err(Error::InvalidRange);
-    // }
+    #[test]
+    fn page_links_on_unaligned_and_out_of_range_pages() {
+        // 最后一页与当前页对齐，沿着 next 翻页能到达 last
+        let page = links(5, 15, 25);
+        assert_eq!(page.prev.as_deref().map(range), Some((0, 5)));
+        assert_eq!(page.next.as_deref().map(range), Some((15, 25)));
+        assert_eq!(range(&page.last), (15, 25));
 
-    Ok(Pagination { start, end })
-}
\ No newline at end of file
+        let page = links(40, 50, 25);
+        assert_eq!(page.prev.as_deref().map(range), Some((30, 40)));
+        assert_eq!(page.next, None);
+        assert_eq!(range(&page.last), (15, 25));
+
+        let page = links(0, 10, 0);
+        assert_eq!((page.prev, page.next), (None, None));
+        assert_eq!(range(&page.last), (0, 10));
+    }
+
+    #[test]
+    fn link_header_lists_relations_in_order() {
+        assert_eq!(
+            links(10, 20, 25).header(),
+            "</questions?sort=id&start=0&end=10>; rel=\"first\", \
+             </questions?sort=id&start=0&end=10>; rel=\"prev\", \
+             </questions?sort=id&start=20&end=30>; rel=\"next\", \
+             </questions?sort=id&start=20&end=30>; rel=\"last\""
+        );
+        assert_eq!(
+            links(0, 10, 5).header(),
+            "</questions?sort=id&start=0&end=10>; rel=\"first\", </questions?sort=id&start=0&end=10>; rel=\"last\""
+        );
+    }
+
+    #[test]
+    fn cursor_round_trips_through_opaque_string() {
+        for cursor in [Cursor::Created(0), Cursor::Created(u64::MAX), Cursor::Id("q/1 ü?&".to_string())] {
+            let encoded = cursor.encode();
+            // URL 安全，可以直接放在查询参数中
+            assert!(encoded.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+            assert_eq!(Cursor::decode(&encoded).unwrap(), cursor);
+        }
+    }
+
+    #[test]
+    fn malformed_cursor_is_rejected() {
+        for cursor in ["not base64!", "", "bnVsbA", &URL_SAFE_NO_PAD.encode(r#"{"Seq":1}"#)] {
+            assert!(matches!(Cursor::decode(cursor), Err(Error::InvalidCursor)), "{}", cursor);
+        }
+    }
+
+    #[test]
+    fn cursor_matches_its_sort_order() {
+        assert!(Cursor::Created(1).matches(SortOrder::Created));
+        assert!(!Cursor::Created(1).matches(SortOrder::Id));
+        assert!(Cursor::Id("a".to_string()).matches(SortOrder::Id));
+        assert!(Cursor::Created(2) > Cursor::Created(1));
+        assert!(Cursor::Id("b".to_string()) > Cursor::Id("a".to_string()));
+    }
+
+    #[test]
+    fn limit_alone_sets_offset_page_size() {
+        assert_eq!(offset(&[]), (0, DEFAULT_LIMIT));
+        assert_eq!(offset(&[("limit", "5")]), (0, 5));
+        assert_eq!(offset(&[("start", "10"), ("limit", "5")]), (10, 15));
+        assert_eq!(offset(&[("start", "10"), ("end", "12")]), (10, 12));
+    }
+
+    #[test]
+    fn cursor_parameter_selects_cursor_pagination() {
+        match request(&[("cursor", ""), ("limit", "5")]) {
+            Ok(PageRequest::Cursor(p)) => assert_eq!((p.after, p.limit), (None, 5)),
+            other => panic!("expected cursor pagination, got {:?}", other),
+        }
+        let cursor = Cursor::Created(7).encode();
+        match request(&[("cursor", cursor.as_str())]) {
+            Ok(PageRequest::Cursor(p)) => assert_eq!((p.after, p.limit), (Some(Cursor::Created(7)), DEFAULT_LIMIT)),
+            other => panic!("expected cursor pagination, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn invalid_combinations_are_rejected() {
+        assert!(matches!(request(&[("limit", "0")]), Err(Error::InvalidLimit(MAX_LIMIT))));
+        assert!(matches!(request(&[("limit", "101")]), Err(Error::InvalidLimit(MAX_LIMIT))));
+        assert!(matches!(request(&[("start", "0"), ("end", "101")]), Err(Error::InvalidLimit(MAX_LIMIT))));
+        assert!(matches!(request(&[("start", "5"), ("end", "2")]), Err(Error::InvalidRange)));
+        assert!(matches!(request(&[("end", "5"), ("limit", "5")]), Err(Error::ConflictingParameters(..))));
+        assert!(matches!(request(&[("cursor", ""), ("start", "5")]), Err(Error::ConflictingParameters(..))));
+        assert!(matches!(request(&[("cursor", "!!")]), Err(Error::InvalidCursor)));
+    }
+}
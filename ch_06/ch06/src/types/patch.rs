@@ -0,0 +1,151 @@
+use handle_errors::Error;
+use serde_json::{Map, Value};
+
+use crate::types::media_type;
+use crate::types::question::Question;
+
+// 可以通过 PATCH 修改的字段，其余字段由服务端维护
+const EDITABLE_FIELDS: &[&str] = &["title", "content", "tags"];
+
+// PATCH 请求体的格式，由 Content-Type 决定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchFormat {
+    // application/merge-patch+json (RFC 7396)
+    Merge,
+    // application/json-patch+json (RFC 6902)
+    Json,
+}
+
+impl PatchFormat {
+    pub fn from_content_type(content_type: &str) -> Result<Self, Error> {
+        let essence = media_type::essence(content_type);
+        match essence.as_str() {
+            "application/merge-patch+json" => Ok(PatchFormat::Merge),
+            "application/json-patch+json" => Ok(PatchFormat::Json),
+            _ => Err(Error::UnsupportedPatchFormat(essence)),
+        }
+    }
+
+    // 在问题的 JSON 表示（与 GET 返回的一致）上应用补丁，再按 Question 的结构解析回来
+    pub fn apply(self, question: &Question, body: &[u8]) -> Result<Question, Error> {
+        let original = serde_json::to_value(question).map_err(|e| Error::InternalError(e.to_string()))?;
+        let mut doc = original.clone();
+        match self {
+            PatchFormat::Merge => {
+                let patch: Value = serde_json::from_slice(body).map_err(|e| Error::InvalidPatch(e.to_string()))?;
+                json_patch::merge(&mut doc, &patch);
+            }
+            PatchFormat::Json => {
+                let patch: json_patch::Patch =
+                    serde_json::from_slice(body).map_err(|e| Error::InvalidPatch(e.to_string()))?;
+                json_patch::patch(&mut doc, &patch).map_err(|e| Error::PatchConflict(e.to_string()))?;
+            }
+        }
+        check_fields(original.as_object(), &doc)?;
+        serde_json::from_value(doc).map_err(|e| Error::InvalidQuestion(e.to_string()))
+    }
+}
+
+// 只读字段可以原样保留，但不能修改、删除，也不能增加未知的字段
+fn check_fields(original: Option<&Map<String, Value>>, patched: &Value) -> Result<(), Error> {
+    let (Some(original), Some(patched)) = (original, patched.as_object()) else {
+        return Err(Error::InvalidQuestion("question must be a JSON object".to_string()));
+    };
+    for (name, value) in patched {
+        if EDITABLE_FIELDS.contains(&name.as_str()) {
+            continue;
+        }
+        match original.get(name) {
+            Some(old) if old == value => {}
+            Some(_) => return Err(Error::InvalidQuestion(format!("field '{}' cannot be modified", name))),
+            None => return Err(Error::InvalidQuestion(format!("unknown field '{}'", name))),
+        }
+    }
+    for name in original.keys() {
+        if !EDITABLE_FIELDS.contains(&name.as_str()) && !patched.contains_key(name) {
+            return Err(Error::InvalidQuestion(format!("field '{}' cannot be removed", name)));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use serde_json::json;
+
+    use super::*;
+    use crate::types::question::{NewQuestion, QuestionId};
+
+    fn question() -> Question {
+        let new = NewQuestion {
+            id: None,
+            title: "First".to_string(),
+            content: "How do I patch a question?".to_string(),
+            tags: Some(vec!["rust".to_string()]),
+        };
+        new.into_question(QuestionId("1".to_string()), "alice".to_string(), Utc::now())
+    }
+
+    fn rejection(result: Result<Question, Error>) -> String {
+        match result {
+            Err(Error::InvalidQuestion(message)) => message,
+            other => panic!("expected InvalidQuestion, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn format_follows_content_type_essence() {
+        assert_eq!(
+            PatchFormat::from_content_type("Application/Merge-Patch+JSON; charset=utf-8").unwrap(),
+            PatchFormat::Merge
+        );
+        assert_eq!(PatchFormat::from_content_type("application/json-patch+json").unwrap(), PatchFormat::Json);
+        assert!(matches!(
+            PatchFormat::from_content_type("application/json"),
+            Err(Error::UnsupportedPatchFormat(essence)) if essence == "application/json"
+        ));
+    }
+
+    #[test]
+    fn merge_patch_edits_only_editable_fields() {
+        let original = question();
+        let patch = json!({"title": "Patched", "tags": null, "id": "1", "version": 1});
+        let patched = PatchFormat::Merge.apply(&original, patch.to_string().as_bytes()).unwrap();
+        assert_eq!(patched.title, "Patched");
+        assert_eq!(patched.content, original.content);
+        assert_eq!(patched.tags, None);
+        assert_eq!(patched.metadata.author, "alice");
+    }
+
+    #[test]
+    fn read_only_fields_cannot_be_modified_removed_or_added() {
+        let original = question();
+        let apply = |patch: serde_json::Value| PatchFormat::Merge.apply(&original, patch.to_string().as_bytes());
+        assert_eq!(rejection(apply(json!({"version": 7}))), "field 'version' cannot be modified");
+        assert_eq!(rejection(apply(json!({"author": null}))), "field 'author' cannot be removed");
+        assert_eq!(rejection(apply(json!({"answers": []}))), "unknown field 'answers'");
+
+        let patch = json!([{"op": "move", "from": "/id", "path": "/title"}]);
+        let moved = PatchFormat::Json.apply(&original, patch.to_string().as_bytes());
+        assert_eq!(rejection(moved), "field 'id' cannot be removed");
+        assert!(matches!(check_fields(None, &json!({})), Err(Error::InvalidQuestion(_))));
+    }
+
+    #[test]
+    fn json_patch_errors_are_reported_separately() {
+        let original = question();
+        let failed_test = json!([{"op": "test", "path": "/title", "value": "Other"}]);
+        assert!(matches!(
+            PatchFormat::Json.apply(&original, failed_test.to_string().as_bytes()),
+            Err(Error::PatchConflict(_))
+        ));
+        assert!(matches!(PatchFormat::Json.apply(&original, b"{}"), Err(Error::InvalidPatch(_))));
+        // 修改后的文档不能解析成 Question
+        let wrong_type = json!([{"op": "replace", "path": "/title", "value": 1}]);
+        assert!(matches!(
+            PatchFormat::Json.apply(&original, wrong_type.to_string().as_bytes()),
+            Err(Error::InvalidQuestion(message)) if message.contains("invalid type")
+        ));
+    }
+}
@@ -1,18 +1,72 @@
 use std::io::ErrorKind;
 use std::str::FromStr;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use crate::types::metadata::Metadata;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Question {
     pub id: QuestionId,
+    // 请求体中缺少时为空字符串，由校验返回字段错误
+    #[serde(default)]
     pub title: String,
+    #[serde(default)]
     pub content: String,
     pub tags: Option<Vec<String>>,
+    // created_at、updated_at、author
+    #[serde(flatten)]
+    pub metadata: Metadata,
+    // 由服务端维护，创建时为 1，每次修改加 1，作为 ETag 返回；旧数据中没有时为 0
+    #[serde(default)]
+    pub version: u64,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct QuestionId(pub String);
 
+// 回收站中的问题
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DeletedQuestion {
+    #[serde(flatten)]
+    pub question: Question,
+    pub deleted_at: DateTime<Utc>,
+    pub deleted_by: String,
+}
+
+// POST /questions 的请求体，id 默认由服务端生成
+#[derive(Clone, Debug, Deserialize)]
+pub struct NewQuestion {
+    // 只有开启 --allow-client-ids 时才会使用，否则忽略
+    pub id: Option<QuestionId>,
+    // 缺少时为空字符串，由校验返回字段错误
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub content: String,
+    pub tags: Option<Vec<String>>,
+}
+
+// PUT /questions/{id} 的请求体，summary 是可选的修改说明
+#[derive(Clone, Debug, Deserialize)]
+pub struct QuestionUpdate {
+    #[serde(flatten)]
+    pub question: Question,
+    pub summary: Option<String>,
+}
+
+impl NewQuestion {
+    pub fn into_question(self, id: QuestionId, author: String, now: DateTime<Utc>) -> Question {
+        Question {
+            id,
+            title: self.title,
+            content: self.content,
+            tags: self.tags,
+            metadata: Metadata::new(author, now),
+            version: 1,
+        }
+    }
+}
+
 impl FromStr for QuestionId {
     type Err = std::io::Error;
 
@@ -0,0 +1,70 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::types::question::{Question, QuestionId};
+
+// 问题的一个版本，保存修改后的完整内容；第 1 个版本是创建时的内容
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Revision {
+    pub number: u64,
+    pub question_id: QuestionId,
+    pub title: String,
+    pub content: String,
+    pub tags: Option<Vec<String>>,
+    pub editor: String,
+    pub created_at: DateTime<Utc>,
+    pub summary: Option<String>,
+}
+
+// 修改问题的人和修改说明
+#[derive(Debug, Clone)]
+pub struct Edit {
+    pub editor: String,
+    pub summary: Option<String>,
+}
+
+impl Edit {
+    // 标签改名、合并或添加别名时自动记录的修改说明，rename 是旧标签 → 新标签，
+    // 只列出问题改名前的标签 tags 中实际出现的旧标签
+    pub fn rename_tags(editor: &str, rename: &BTreeMap<String, String>, tags: &Option<Vec<String>>) -> Self {
+        let renamed: Vec<(&String, &String)> = rename
+            .iter()
+            .filter(|(from, _)| tags.iter().flatten().any(|tag| tag == *from))
+            .collect();
+        let from: Vec<String> = renamed.iter().map(|(from, _)| format!("'{}'", from)).collect();
+        let summary = match renamed.first() {
+            Some((_, to)) if renamed.len() == 1 => format!("renamed tag {} to '{}'", from[0], to),
+            Some((_, to)) => format!("merged tags {} into '{}'", from.join(", "), to),
+            None => "renamed tags".to_string(),
+        };
+        Edit {
+            editor: editor.to_string(),
+            summary: Some(summary),
+        }
+    }
+}
+
+impl Revision {
+    pub fn new(number: u64, id: &QuestionId, question: &Question, edit: &Edit) -> Self {
+        Revision {
+            number,
+            question_id: id.clone(),
+            title: question.title.clone(),
+            content: question.content.clone(),
+            tags: question.tags.clone(),
+            editor: edit.editor.clone(),
+            created_at: question.metadata.updated_at,
+            summary: edit.summary.clone(),
+        }
+    }
+
+    // 创建问题时的版本
+    pub fn initial(question: &Question) -> Self {
+        let edit = Edit {
+            editor: question.metadata.author.clone(),
+            summary: None,
+        };
+        Revision::new(1, &question.id, question, &edit)
+    }
+}
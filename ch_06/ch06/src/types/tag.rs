@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use handle_errors::Error;
+use serde::{Deserialize, Serialize};
+
+// ?tag_match=any（默认，包含任意一个标签）或 ?tag_match=all（包含全部标签）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TagMatch {
+    #[default]
+    Any,
+    All,
+}
+
+impl TagMatch {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TagMatch::Any => "any",
+            TagMatch::All => "all",
+        }
+    }
+}
+
+impl FromStr for TagMatch {
+    type Err = Error;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "any" => Ok(TagMatch::Any),
+            "all" => Ok(TagMatch::All),
+            _ => Err(Error::InvalidTagMatch(mode.to_string())),
+        }
+    }
+}
+
+// GET /tags 的列表项
+#[derive(Debug, Clone, Serialize)]
+pub struct TagCount {
+    pub name: String,
+    pub count: usize,
+}
+
+// 写入问题时 alias 会被替换成 tag
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagAlias {
+    pub alias: String,
+    pub tag: String,
+}
+
+// POST /tags/rename 的请求体，to 已经被使用时两个标签合并
+#[derive(Debug, Deserialize)]
+pub struct RenameTag {
+    pub from: String,
+    pub to: String,
+}
+
+// POST /tags/merge 的请求体，把 tags 中的标签全部合并到 into
+#[derive(Debug, Deserialize)]
+pub struct MergeTags {
+    pub tags: Vec<String>,
+    pub into: String,
+}
+
+// 去掉首尾空白、把连续的空白合并成一个空格并转成小写，空标签返回 None
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    match tag.is_empty() {
+        true => None,
+        false => Some(tag),
+    }
+}
+
+// 接口参数中的标签不能为空
+pub fn required_tag(tag: &str) -> Result<String, Error> {
+    normalize_tag(tag).ok_or(Error::EmptyTag)
+}
+
+// 规范化标签并替换别名，去掉空标签和重复的标签，保持原来的顺序
+pub fn resolve_tags(tags: Option<Vec<String>>, aliases: &BTreeMap<String, String>) -> Option<Vec<String>> {
+    tags.map(|tags| {
+        let mut resolved: Vec<String> = Vec::with_capacity(tags.len());
+        for tag in tags.iter().filter_map(|tag| normalize_tag(tag)) {
+            let tag = aliases.get(&tag).cloned().unwrap_or(tag);
+            if !resolved.contains(&tag) {
+                resolved.push(tag);
+            }
+        }
+        resolved
+    })
+}
+
+// 合并标签时每个标签改名为 into 的对照表，into 本身不需要改名
+pub fn merge_map(tags: Vec<String>, into: &str) -> BTreeMap<String, String> {
+    tags.into_iter()
+        .filter(|tag| tag != into)
+        .map(|tag| (tag, into.to_string()))
+        .collect()
+}
+
+// 按标签过滤问题，tags 已规范化、去重且不为空
+#[derive(Debug, Clone)]
+pub struct TagFilter {
+    pub tags: Vec<String>,
+    pub mode: TagMatch,
+}
+
+impl TagFilter {
+    // 把别名替换成对应的标签，替换后可能出现重复
+    pub fn resolve(&self, aliases: &BTreeMap<String, String>) -> TagFilter {
+        TagFilter {
+            tags: resolve_tags(Some(self.tags.clone()), aliases).unwrap_or_default(),
+            mode: self.mode,
+        }
+    }
+
+    // 翻页链接中需要保留的参数，例如 ["tag=rust", "tag=warp", "tag_match=all"]
+    pub fn query(&self) -> Vec<String> {
+        let mut query: Vec<String> = self
+            .tags
+            .iter()
+            .map(|tag| serde_urlencoded::to_string([("tag", tag)]).unwrap_or_default())
+            .collect();
+        if self.mode != TagMatch::default() {
+            query.push(format!("tag_match={}", self.mode.as_str()));
+        }
+        query
+    }
+}
+
+// 取出所有 tag 和 tag_match 参数，剩下的参数用于排序和分页；没有 tag 时不过滤
+pub fn extract_tag_filter(params: &mut Vec<(String, String)>) -> Result<Option<TagFilter>, Error> {
+    let mut tags: Vec<String> = Vec::new();
+    let mut mode = TagMatch::default();
+    let mut rest = Vec::with_capacity(params.len());
+    for (key, value) in params.drain(..) {
+        match key.as_str() {
+            "tag" => match normalize_tag(&value) {
+                Some(tag) if !tags.contains(&tag) => tags.push(tag),
+                _ => {}
+            },
+            "tag_match" => mode = value.parse()?,
+            _ => rest.push((key, value)),
+        }
+    }
+    *params = rest;
+
+    match tags.is_empty() {
+        true => Ok(None),
+        false => Ok(Some(TagFilter { tags, mode })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(tags: &[&str]) -> Option<Vec<String>> {
+        Some(tags.iter().map(|tag| tag.to_string()).collect())
+    }
+
+    #[test]
+    fn normalize_collapses_whitespace_and_case() {
+        assert_eq!(normalize_tag("  Rust \t Lang "), Some("rust lang".to_string()));
+        assert_eq!(normalize_tag(" \n "), None);
+        assert!(matches!(required_tag(""), Err(Error::EmptyTag)));
+    }
+
+    #[test]
+    fn resolve_replaces_aliases_and_removes_duplicates() {
+        let aliases = BTreeMap::from([
+            ("rust-lang".to_string(), "rust".to_string()),
+            ("warp-rs".to_string(), "warp".to_string()),
+        ]);
+        assert_eq!(
+            resolve_tags(tags(&["Warp-RS", "web", "", "rust-lang", "RUST", "web "]), &aliases),
+            tags(&["warp", "web", "rust"])
+        );
+        assert_eq!(resolve_tags(tags(&[]), &aliases), tags(&[]));
+        assert_eq!(resolve_tags(None, &aliases), None);
+    }
+
+    #[test]
+    fn merge_map_skips_the_target_tag() {
+        let map = merge_map(vec!["a".to_string(), "b".to_string(), "c".to_string()], "b");
+        assert_eq!(map, BTreeMap::from([("a".to_string(), "b".to_string()), ("c".to_string(), "b".to_string())]));
+        assert_eq!(resolve_tags(tags(&["a", "b", "c", "d"]), &map), tags(&["b", "d"]));
+    }
+
+    #[test]
+    fn tag_filter_query_keeps_tags_and_mode() {
+        let filter = TagFilter { tags: vec!["c#".to_string(), "web dev".to_string()], mode: TagMatch::All };
+        assert_eq!(filter.query(), ["tag=c%23", "tag=web+dev", "tag_match=all"]);
+        let mut params = vec![
+            ("tag".to_string(), " Rust ".to_string()),
+            ("tag".to_string(), "rust".to_string()),
+            ("sort".to_string(), "id".to_string()),
+        ];
+        let filter = extract_tag_filter(&mut params).unwrap().unwrap();
+        assert_eq!((filter.tags, filter.mode), (vec!["rust".to_string()], TagMatch::Any));
+        assert_eq!(params, [("sort".to_string(), "id".to_string())]);
+    }
+}
@@ -0,0 +1,338 @@
+use std::collections::BTreeMap;
+
+use handle_errors::{Error, FieldError};
+
+use crate::types::answer::NewAnswer;
+use crate::types::question::{NewQuestion, Question, QuestionUpdate};
+use crate::types::tag::{normalize_tag, resolve_tags};
+
+pub const MAX_TITLE_LENGTH: usize = 200;
+pub const MAX_CONTENT_LENGTH: usize = 20_000;
+pub const MAX_ANSWER_LENGTH: usize = 20_000;
+pub const MAX_TAGS: usize = 10;
+pub const MAX_TAG_LENGTH: usize = 32;
+pub const MAX_SUMMARY_LENGTH: usize = 200;
+pub const MAX_ID_LENGTH: usize = 64;
+
+// 输入内容的长度和数量上限，启动参数可以修改
+#[derive(Debug, Clone, Copy)]
+pub struct ValidationLimits {
+    pub max_title_length: usize,
+    pub max_content_length: usize,
+    pub max_answer_length: usize,
+    pub max_tags: usize,
+    pub max_tag_length: usize,
+}
+
+impl Default for ValidationLimits {
+    fn default() -> Self {
+        ValidationLimits {
+            max_title_length: MAX_TITLE_LENGTH,
+            max_content_length: MAX_CONTENT_LENGTH,
+            max_answer_length: MAX_ANSWER_LENGTH,
+            max_tags: MAX_TAGS,
+            max_tag_length: MAX_TAG_LENGTH,
+        }
+    }
+}
+
+// 字段的校验规则，长度按字符数计算
+#[derive(Debug, Clone, Copy)]
+pub enum Rule {
+    // 去掉首尾空白后不能为空
+    Required,
+    MaxLength(usize),
+    // 列表最多包含的元素个数
+    MaxItems(usize),
+    // 标签只能包含字母、数字、空格和 - + # . _
+    TagCharset,
+    // 客户端指定的 id 会出现在 URL 路径中，只能包含 ASCII 字母、数字和 - _
+    IdCharset,
+}
+
+impl Rule {
+    fn code(&self) -> &'static str {
+        match self {
+            Rule::Required => "required",
+            Rule::MaxLength(_) => "too_long",
+            Rule::MaxItems(_) => "too_many_items",
+            Rule::TagCharset | Rule::IdCharset => "invalid_characters",
+        }
+    }
+
+    // 文本不满足规则时返回错误信息；MaxItems 不适用于文本
+    fn check_text(&self, value: &str) -> Option<String> {
+        match *self {
+            Rule::Required if value.is_empty() => Some("must not be empty".to_string()),
+            Rule::MaxLength(max) if value.chars().count() > max => {
+                Some(format!("must be at most {} characters", max))
+            }
+            Rule::TagCharset if !value.chars().all(is_tag_char) => {
+                Some("may only contain letters, digits, spaces and - + # . _".to_string())
+            }
+            Rule::IdCharset if !value.chars().all(is_id_char) => {
+                Some("may only contain ASCII letters, digits, - and _".to_string())
+            }
+            _ => None,
+        }
+    }
+
+    fn check_items(&self, count: usize) -> Option<String> {
+        match *self {
+            Rule::MaxItems(max) if count > max => Some(format!("must contain at most {} items", max)),
+            _ => None,
+        }
+    }
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, ' ' | '-' | '+' | '#' | '.' | '_')
+}
+
+fn is_id_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_')
+}
+
+// 收集所有字段的错误，最后一起返回，客户端可以一次看到全部问题
+#[derive(Debug, Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    fn push(&mut self, field: &str, rule: &Rule, message: String) {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            code: rule.code(),
+            message,
+        });
+    }
+
+    // 先去掉首尾空白，再按顺序检查每条规则
+    pub fn text(&mut self, field: &str, value: &mut String, rules: &[Rule]) {
+        let trimmed = value.trim();
+        if trimmed.len() != value.len() {
+            *value = trimmed.to_string();
+        }
+        for rule in rules {
+            if let Some(message) = rule.check_text(value) {
+                self.push(field, rule, message);
+            }
+        }
+    }
+
+    // 可选的文本字段，去掉空白后为空时视为没有提供
+    pub fn optional_text(&mut self, field: &str, value: &mut Option<String>, rules: &[Rule]) {
+        if let Some(text) = value {
+            self.text(field, text, rules);
+            if text.is_empty() {
+                *value = None;
+            }
+        }
+    }
+
+    // 检查去重后的标签个数（list_rules）和规范化后的每个标签（tag_rules）；
+    // 错误中的下标是客户端提交的列表中的位置，空标签直接去掉
+    pub fn tags(&mut self, field: &str, tags: &mut Option<Vec<String>>, list_rules: &[Rule], tag_rules: &[Rule]) {
+        let Some(list) = tags.take() else {
+            return;
+        };
+        let normalized: Vec<(usize, String)> = list
+            .iter()
+            .enumerate()
+            .filter_map(|(i, tag)| Some((i, normalize_tag(tag)?)))
+            .collect();
+        let resolved = resolve_tags(Some(normalized.iter().map(|(_, tag)| tag.clone()).collect()), &BTreeMap::new());
+        let count = resolved.as_ref().map_or(0, Vec::len);
+        for rule in list_rules {
+            if let Some(message) = rule.check_items(count) {
+                self.push(field, rule, message);
+            }
+        }
+        for (i, mut tag) in normalized {
+            self.text(&format!("{}[{}]", field, i), &mut tag, tag_rules);
+        }
+        *tags = resolved;
+    }
+
+    pub fn finish(self) -> Result<(), Error> {
+        match self.errors.is_empty() {
+            true => Ok(()),
+            false => Err(Error::Validation(self.errors)),
+        }
+    }
+}
+
+// 客户端提交的内容在写入存储前按声明的规则检查，同时去掉多余的空白
+pub trait Validate {
+    fn validate(&mut self, limits: &ValidationLimits) -> Result<(), Error>;
+}
+
+fn question_fields(
+    validator: &mut Validator,
+    limits: &ValidationLimits,
+    title: &mut String,
+    content: &mut String,
+    tags: &mut Option<Vec<String>>,
+) {
+    validator.text("title", title, &[Rule::Required, Rule::MaxLength(limits.max_title_length)]);
+    validator.text("content", content, &[Rule::Required, Rule::MaxLength(limits.max_content_length)]);
+    validator.tags(
+        "tags",
+        tags,
+        &[Rule::MaxItems(limits.max_tags)],
+        &[Rule::MaxLength(limits.max_tag_length), Rule::TagCharset],
+    );
+}
+
+impl Validate for NewQuestion {
+    fn validate(&mut self, limits: &ValidationLimits) -> Result<(), Error> {
+        let mut validator = Validator::default();
+        // 空的 id 视为没有提供，由服务端生成
+        if let Some(id) = &mut self.id {
+            validator.text("id", &mut id.0, &[Rule::MaxLength(MAX_ID_LENGTH), Rule::IdCharset]);
+        }
+        question_fields(&mut validator, limits, &mut self.title, &mut self.content, &mut self.tags);
+        validator.finish()
+    }
+}
+
+// PUT 的请求体和 PATCH 之后的结果
+impl Validate for Question {
+    fn validate(&mut self, limits: &ValidationLimits) -> Result<(), Error> {
+        let mut validator = Validator::default();
+        question_fields(&mut validator, limits, &mut self.title, &mut self.content, &mut self.tags);
+        validator.finish()
+    }
+}
+
+impl Validate for QuestionUpdate {
+    fn validate(&mut self, limits: &ValidationLimits) -> Result<(), Error> {
+        let mut validator = Validator::default();
+        let question = &mut self.question;
+        question_fields(&mut validator, limits, &mut question.title, &mut question.content, &mut question.tags);
+        validator.optional_text("summary", &mut self.summary, &[Rule::MaxLength(MAX_SUMMARY_LENGTH)]);
+        validator.finish()
+    }
+}
+
+impl Validate for NewAnswer {
+    fn validate(&mut self, limits: &ValidationLimits) -> Result<(), Error> {
+        let mut validator = Validator::default();
+        validator.text("content", &mut self.content, &[Rule::Required, Rule::MaxLength(limits.max_answer_length)]);
+        validator.text("questionId", &mut self.question_id.0, &[Rule::Required]);
+        validator.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::answer::NewAnswerFields;
+
+    fn errors(result: Result<(), Error>) -> Vec<(String, &'static str)> {
+        match result {
+            Ok(()) => Vec::new(),
+            Err(Error::Validation(errors)) => errors.into_iter().map(|e| (e.field, e.code)).collect(),
+            Err(other) => panic!("expected Validation, got {:?}", other),
+        }
+    }
+
+    fn new_question(json: &str) -> NewQuestion {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn field(name: &str, code: &'static str) -> (String, &'static str) {
+        (name.to_string(), code)
+    }
+
+    #[test]
+    fn rules_check_trimmed_text_by_characters() {
+        assert_eq!(Rule::Required.check_text(""), Some("must not be empty".to_string()));
+        assert_eq!(Rule::MaxLength(3).check_text("äöü"), None);
+        assert!(Rule::MaxLength(3).check_text("äöüß").is_some());
+        assert_eq!(Rule::TagCharset.check_text("c# c++ .net_core-9"), None);
+        assert!(Rule::TagCharset.check_text("bad$").is_some());
+        assert_eq!(Rule::MaxItems(2).check_items(2), None);
+        assert!(Rule::MaxItems(2).check_items(3).is_some());
+        assert_eq!(Rule::MaxItems(2).check_text("ignored"), None);
+
+        let mut question = new_question(r#"{"title": "  Title \n", "content": " body ", "tags": [" Rust  Lang "]}"#);
+        question.validate(&ValidationLimits::default()).unwrap();
+        assert_eq!((question.title.as_str(), question.content.as_str()), ("Title", "body"));
+        assert_eq!(question.tags, Some(vec!["rust lang".to_string()]));
+    }
+
+    #[test]
+    fn tag_errors_use_the_submitted_index() {
+        let mut question = new_question(r#"{"title": "t", "content": "c", "tags": ["", "rust", "RUST", "bad$"]}"#);
+        assert_eq!(errors(question.validate(&ValidationLimits::default())), [field("tags[3]", "invalid_characters")]);
+
+        let mut question = new_question(r#"{"title": "t", "content": "c", "tags": [" ", "Rust", "rust", "web"]}"#);
+        question.validate(&ValidationLimits::default()).unwrap();
+        assert_eq!(question.tags, Some(vec!["rust".to_string(), "web".to_string()]));
+    }
+
+    #[test]
+    fn limits_are_configurable_and_all_errors_are_reported() {
+        let limits = ValidationLimits {
+            max_title_length: 5,
+            max_content_length: 10,
+            max_answer_length: 3,
+            max_tags: 2,
+            max_tag_length: 4,
+        };
+        let mut question = new_question(r#"{"title": "too long", "content": "", "tags": ["a", "b", "A", "long tag"]}"#);
+        assert_eq!(
+            errors(question.validate(&limits)),
+            [
+                field("title", "too_long"),
+                field("content", "required"),
+                field("tags", "too_many_items"),
+                field("tags[3]", "too_long"),
+            ]
+        );
+        // 重复的标签只算一个
+        let mut question = new_question(r#"{"title": "ok", "content": "ok", "tags": ["a", "b", "A"]}"#);
+        assert_eq!(errors(question.validate(&limits)), []);
+
+        let mut update: QuestionUpdate = serde_json::from_str(&format!(
+            r#"{{"id": "1", "title": "ok", "content": "ok", "summary": "{}"}}"#,
+            "s".repeat(MAX_SUMMARY_LENGTH + 1)
+        ))
+        .unwrap();
+        assert_eq!(errors(update.validate(&limits)), [field("summary", "too_long")]);
+    }
+
+    #[test]
+    fn client_ids_are_limited_to_url_safe_characters() {
+        let limits = ValidationLimits::default();
+        let mut question = new_question(r#"{"id": " q-1_A ", "title": "t", "content": "c"}"#);
+        question.validate(&limits).unwrap();
+        assert_eq!(question.id.map(|id| id.0), Some("q-1_A".to_string()));
+
+        for id in ["a/b", "a?b", "a#b", "a b", "a\\nb", "é"] {
+            let mut question = new_question(&format!(r#"{{"id": "{}", "title": "t", "content": "c"}}"#, id));
+            assert_eq!(errors(question.validate(&limits)), [field("id", "invalid_characters")], "{}", id);
+        }
+        let long = "a".repeat(MAX_ID_LENGTH + 1);
+        let mut question = new_question(&format!(r#"{{"id": "{}", "title": "t", "content": "c"}}"#, long));
+        assert_eq!(errors(question.validate(&limits)), [field("id", "too_long")]);
+    }
+
+    #[test]
+    fn missing_fields_are_field_errors() {
+        let mut question = new_question("{}");
+        assert_eq!(
+            errors(question.validate(&ValidationLimits::default())),
+            [field("title", "required"), field("content", "required")]
+        );
+
+        let fields: NewAnswerFields = serde_urlencoded::from_str("").unwrap();
+        let mut answer = NewAnswer::from(fields);
+        assert_eq!(
+            errors(answer.validate(&ValidationLimits::default())),
+            [field("content", "required"), field("questionId", "required")]
+        );
+    }
+}